@@ -6,14 +6,25 @@ task.spawn(function()
 
 	-- 配置常量
 	local IS_DEBUG = false -- 调试模式开关
-	local BASE_URL = "http://127.0.0.1:28860" -- 本地服务器地址
+	local HOST = "127.0.0.1" -- 本地服务器地址
+	local BASE_PORT = 28860 -- 本地服务器默认端口
+	local PORT_RANGE = 10 -- 端口被占用时CLI会依次使用之后的端口, 连接失败或CLI返回421时按顺序尝试
 	local POLLING_INTERVAL = 0.7 -- 轮询间隔(秒)
+	local PORT_SCAN_INTERVAL = 0.1 -- 端口上没有CLI时, 尝试下一个端口前的等待(秒)
 
 	-- 如果游戏正在运行中，则不执行插件逻辑
 	if RunService:IsRunning() then
 		return
 	end
 
+	-- 当前尝试的端口偏移
+	local portOffset = 0
+
+	-- 获取当前服务器地址
+	local function getBaseUrl()
+		return "http://" .. HOST .. ":" .. tostring(BASE_PORT + portOffset)
+	end
+
 	-- 调试日志打印函数
 	local function debugPrint(...)
 		if IS_DEBUG then
@@ -160,7 +171,7 @@ return nil
 
 			debugPrint("[调试] 发送结果到服务器...")
 			local ok, serverResponse = pcall(HttpService.RequestAsync, HttpService, {
-				Url = BASE_URL .. "/results",
+				Url = getBaseUrl() .. "/results",
 				Method = "POST",
 				Headers = Headers,
 				Body = HttpService:JSONEncode(results),
@@ -185,7 +196,7 @@ return nil
 	while true do
 		debugPrint("[调试] 发送轮询请求...")
 		local ok, serverResponse = pcall(HttpService.RequestAsync, HttpService, {
-			Url = BASE_URL .. "/poll",
+			Url = getBaseUrl() .. "/poll",
 			Method = "GET",
 			Headers = identifierHeaders,
 		})
@@ -203,7 +214,7 @@ return nil
 				if IS_DEBUG then
 					debugPrint("[调试] 捕获日志: " .. message)
 					pcall(HttpService.RequestAsync, HttpService, {
-						Url = BASE_URL .. "/logs",
+						Url = getBaseUrl() .. "/logs",
 						Method = "POST",
 						Body = HttpService:JSONEncode({
 							message = message,
@@ -314,6 +325,10 @@ return nil
 		elseif ok and serverResponse.StatusCode == 204 then
 			-- 正常的空响应，继续轮询
 			debugPrint("[调试] 轮询响应204，暂无测试任务")
+		elseif ok and serverResponse.StatusCode == 421 then
+			-- 该CLI没有选择当前place(可能属于其他项目), 轮询下一个端口
+			debugPrint("[调试] 轮询响应421，尝试下一个端口: " .. getBaseUrl())
+			portOffset = (portOffset + 1) % PORT_RANGE
 		elseif ok then
 			-- 其他非预期响应
 			log(warn, "从服务器收到非预期的响应: " .. serverResponse.StatusCode)
@@ -323,11 +338,16 @@ return nil
 				debugPrint("[调试] 响应体: " .. serverResponse.Body)
 			end
 		else
-			-- 连接错误，但不打印警告，因为服务器可能尚未启动
-			debugPrint("[调试] 轮询连接失败，服务器可能未启动")
+			-- 连接错误，但不打印警告，因为服务器可能尚未启动; 下一轮尝试下一个端口
+			debugPrint("[调试] 轮询连接失败，服务器可能未启动: " .. getBaseUrl())
+			portOffset = (portOffset + 1) % PORT_RANGE
 		end
 
 		debugPrint("[调试] 等待下一轮轮询...")
-		task.wait(POLLING_INTERVAL)
+		if ok then
+			task.wait(POLLING_INTERVAL)
+		else
+			task.wait(PORT_SCAN_INTERVAL)
+		end
 	end
 end)
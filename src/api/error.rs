@@ -0,0 +1,71 @@
+use axum::{
+    extract::ConnectInfo,
+    http::{request::Parts, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use console::style;
+use std::{fmt, net::SocketAddr};
+
+/// 客户端请求无效时返回的错误, 响应为 400 和 `{"error": "..."}`
+#[derive(Debug)]
+pub enum ApiError {
+    /// 缺少必需的请求头
+    MissingHeader(&'static str),
+    /// 请求头的值无法解析
+    InvalidHeader { name: &'static str, value: String },
+    /// 请求体不是有效的JSON, 或与预期的结构不符
+    InvalidBody(String),
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::MissingHeader(name) => write!(f, "缺少请求头 {}", name),
+            ApiError::InvalidHeader { name, value } => {
+                write!(f, "请求头 {} 的值无效: {:?}", name, value)
+            }
+            ApiError::InvalidBody(reason) => write!(f, "请求体无效: {}", reason),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = serde_json::json!({ "error": self.to_string() });
+        (StatusCode::BAD_REQUEST, Json(body)).into_response()
+    }
+}
+
+impl ApiError {
+    /// 在CLI中输出警告, 指出发送无效请求的客户端
+    pub fn warn(&self, parts: &Parts) {
+        let mut client = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.to_string())
+            .unwrap_or_else(|| "未知客户端".to_string());
+
+        let header = |name: &str| {
+            parts
+                .headers
+                .get(name)
+                .map(|value| String::from_utf8_lossy(value.as_bytes()).into_owned())
+        };
+        match (header("place-name"), header("place-guid")) {
+            (Some(name), Some(guid)) => client += &format!(" (place {}, guid {})", name, guid),
+            (Some(name), None) => client += &format!(" (place {})", name),
+            (None, Some(guid)) => client += &format!(" (guid {})", guid),
+            (None, None) => {}
+        }
+
+        eprintln!(
+            "{}",
+            style(format!(
+                "忽略来自 {} 的无效请求 {} {}: {}",
+                client, parts.method, parts.uri, self
+            ))
+            .yellow()
+        );
+    }
+}
@@ -0,0 +1,75 @@
+use super::error::ApiError;
+use axum::{
+    async_trait,
+    extract::{FromRequest, FromRequestParts, Request},
+    http::{request::Parts, HeaderMap},
+    Json,
+};
+use serde::de::DeserializeOwned;
+
+/// 插件轮询时用于标识place的请求头
+#[derive(Debug)]
+pub struct PlaceHeaders {
+    pub guid: String,
+    pub name: String,
+    pub id: u64,
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for PlaceHeaders {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parse_place_headers(&parts.headers).inspect_err(|e| e.warn(parts))
+    }
+}
+
+fn parse_place_headers(headers: &HeaderMap) -> Result<PlaceHeaders, ApiError> {
+    let id = header_str(headers, "place-id")?;
+    Ok(PlaceHeaders {
+        guid: header_str(headers, "place-guid")?,
+        name: header_str(headers, "place-name")?,
+        id: id.trim().parse().map_err(|_| ApiError::InvalidHeader {
+            name: "place-id",
+            value: id.clone(),
+        })?,
+    })
+}
+
+/// 读取请求头; place名称可能包含非ASCII字符, 因此按UTF-8解析
+fn header_str(headers: &HeaderMap, name: &'static str) -> Result<String, ApiError> {
+    let value = headers.get(name).ok_or(ApiError::MissingHeader(name))?;
+    std::str::from_utf8(value.as_bytes())
+        .map(str::to_string)
+        .map_err(|_| ApiError::InvalidHeader {
+            name,
+            value: String::from_utf8_lossy(value.as_bytes()).into_owned(),
+        })
+}
+
+/// 与 `Json` 相同, 但解析失败时返回 `ApiError` 并在CLI中输出警告
+#[derive(Debug)]
+pub struct ApiJson<T>(pub T);
+
+#[async_trait]
+impl<S, T> FromRequest<S> for ApiJson<T>
+where
+    S: Send + Sync,
+    T: DeserializeOwned,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let (parts, body) = req.into_parts();
+        let req = Request::from_parts(parts.clone(), body);
+
+        match Json::<T>::from_request(req, state).await {
+            Ok(Json(value)) => Ok(ApiJson(value)),
+            Err(rejection) => {
+                let error = ApiError::InvalidBody(rejection.body_text());
+                error.warn(&parts);
+                Err(error)
+            }
+        }
+    }
+}
@@ -1,12 +1,12 @@
-use axum::{http::StatusCode, Json};
+use super::extract::ApiJson;
+use axum::http::StatusCode;
 use console::style;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
 use serde_repr::{Deserialize_repr, Serialize_repr};
 
 #[derive(Serialize_repr, Deserialize_repr, Debug)]
 #[repr(u8)]
-enum MessageType {
+pub enum MessageType {
     Output = 0,
     Info = 1,
     Warning = 2,
@@ -15,14 +15,12 @@ enum MessageType {
 
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
-struct Log {
-    message: String,
-    message_type: MessageType,
+pub struct Log {
+    pub message: String,
+    pub message_type: MessageType,
 }
 
-pub async fn logs(Json(body): Json<Value>) -> StatusCode {
-    let log: Log = serde_json::from_value(body).unwrap();
-
+pub async fn logs(ApiJson(log): ApiJson<Log>) -> StatusCode {
     match log.message_type {
         MessageType::Output | MessageType::Info => {
             println!("Output: {}", log.message);
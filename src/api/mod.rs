@@ -1,3 +1,6 @@
+pub mod error;
+pub mod extract;
+
 pub mod poll;
 pub use poll::poll;
 
@@ -1,62 +1,53 @@
+use super::extract::PlaceHeaders;
 use crate::{
     config::ConfigResponse,
     state::{AppState, Place},
 };
-use axum::{
-    extract::{Request, State},
-    http::StatusCode,
-    Json,
-};
+use axum::{extract::State, http::StatusCode, Json};
 use console::style;
-use std::sync::Arc;
+use std::{
+    sync::{atomic::Ordering, Arc},
+    time::Instant,
+};
 
 pub async fn poll(
     State(state): State<Arc<AppState>>,
-    req: Request,
+    headers: PlaceHeaders,
 ) -> Result<Json<ConfigResponse>, StatusCode> {
-    let headers_map = req.headers();
-    let place_guid = headers_map
-        .get("place-guid")
-        .and_then(|hv| hv.to_str().ok())
-        .unwrap();
-    let place_name = headers_map
-        .get("place-name")
-        .and_then(|hv| hv.to_str().ok())
-        .unwrap();
-    let place_id_str = headers_map
-        .get("place-id")
-        .and_then(|hv| hv.to_str().ok())
-        .unwrap();
-    let place_id = place_id_str.parse::<u64>().unwrap();
-
+    let place_guid = headers.guid.as_str();
     let place = Place {
-        name: place_name.to_string(),
-        id: place_id,
+        name: headers.name,
+        id: headers.id,
     };
 
     // 存储place信息，无论它是否是active_place
     state.places.insert(place_guid.to_string(), place.clone());
 
+    // 活跃place由启动阶段的place选择决定, 见 places::select_active_place
     let mut active_place_lock = state.active_place.lock().await;
 
-    // 如果没有active_place，则将当前place设置为active_place
-    if active_place_lock.is_none() {
-        if state.debug {
-            eprintln!(
-                "{}",
-                style(format!(
-                    "设置place {} ({}) 为活跃place",
-                    place.name, place.id
-                ))
-                .green()
-            );
-        }
+    // 指定了 --place-* 参数时, 直接激活第一个匹配的place
+    if active_place_lock.is_none()
+        && !state.place_filter.is_empty()
+        && state.place_filter.matches(place_guid, &place)
+    {
+        eprintln!(
+            "{}",
+            style(format!("在place {} ({}) 中运行测试", place.name, place.id)).green()
+        );
         active_place_lock.replace(place_guid.to_string());
     }
 
     // 检查当前place是否是active_place
     match active_place_lock.as_deref() {
         Some(active_place) if active_place == place_guid => {
+            // 没有待运行的测试时返回204, 插件会继续轮询
+            if !state.run_pending.swap(false, Ordering::SeqCst) {
+                return Err(StatusCode::NO_CONTENT);
+            }
+            let run_cli_options = state.take_run_cli_options();
+            state.dispatched_at.lock().unwrap().replace(Instant::now());
+
             if state.debug {
                 eprintln!(
                     "{}",
@@ -67,7 +58,7 @@ pub async fn poll(
                 eprintln!("发送的配置详情:");
                 eprintln!("  test_roots: {:?}", state.config.roots);
 
-                if let Some(run_cli_options) = &state.config.run_cli_options {
+                if let Some(run_cli_options) = &run_cli_options {
                     eprintln!("  runCLI-options:");
                     if let Some(ci) = run_cli_options.ci {
                         eprintln!("    ci: {}", ci);
@@ -101,26 +92,23 @@ pub async fn poll(
             Ok(Json(ConfigResponse {
                 test_roots: state.config.roots.clone(),
                 test_extra_options: state.config.test_extra_options.clone().unwrap_or_default(),
-                run_cli_options: state.config.run_cli_options.clone(),
+                run_cli_options,
             }))
         }
         _ => {
-            // 即使不是活跃place，也返回OK，但使用空的test_roots以避免运行测试
+            // 尚未选定活跃place, 或不是活跃place时返回421, 插件会转而轮询下一个端口,
+            // 使同时运行的其他项目的CLI也能收到该place的轮询
             if state.debug {
                 eprintln!(
                     "{}",
                     style(format!(
-                        "收到来自非活跃place {} 的请求，返回空配置",
-                        place_guid
+                        "收到来自非活跃place {} ({}) 的请求",
+                        place.name, place_guid
                     ))
-                    .yellow()
+                    .dim()
                 );
             }
-            Ok(Json(ConfigResponse {
-                test_roots: Vec::new(),
-                test_extra_options: state.config.test_extra_options.clone().unwrap_or_default(),
-                run_cli_options: state.config.run_cli_options.clone(),
-            }))
+            Err(StatusCode::MISDIRECTED_REQUEST)
         }
     }
 }
@@ -1,15 +1,23 @@
+use super::extract::ApiJson;
 use crate::{
-    jest_results::{print_jest_test_results, JestResults},
-    rojo_sourcemap::{get_sourcemap, RojoSourceMap},
-    state::AppState,
+    exit_code::ExitCode, jest_results::JestResults, reporters::report_results, state::AppState,
+    watch,
+};
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
 };
-use axum::{extract::State, http::StatusCode, Json};
 use console::style;
 use serde_json::Value;
-use std::{fs::File, io::Write, process::exit, sync::Arc, time::Duration};
-use tokio::{spawn, time::sleep};
+use std::{fs::File, io::Write, sync::Arc};
+
+pub async fn results(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    ApiJson(body): ApiJson<Value>,
+) -> StatusCode {
+    state.dispatched_at.lock().unwrap().take();
 
-pub async fn results(State(state): State<Arc<AppState>>, Json(body): Json<Value>) -> StatusCode {
     // 保存接收到的JSON以便调试
     let json_str = serde_json::to_string_pretty(&body).unwrap_or_default();
 
@@ -26,144 +34,182 @@ pub async fn results(State(state): State<Arc<AppState>>, Json(body): Json<Value>
         }
     }
 
-    // 首先尝试解析为Jest结果格式
-    let jest_result = serde_json::from_value::<JestResults>(body.clone());
+    // 插件无法加载或运行Jest时会带上 caught-jest-error 请求头
+    let caught_jest_error = headers
+        .get("caught-jest-error")
+        .and_then(|hv| hv.to_str().ok())
+        .is_some_and(|value| value == "true");
+
+    let exit_code = if caught_jest_error {
+        report_jest_load_failure(&body);
+        ExitCode::JestLoadFailure
+    } else {
+        // 首先尝试解析为Jest结果格式
+        match serde_json::from_value::<JestResults>(body.clone()) {
+            Ok(jest_results) => {
+                if state.debug {
+                    eprintln!("{}", style("成功解析为Jest测试结果格式").green());
+                }
+
+                // 监听模式下每轮输出前清屏
+                if state.watch {
+                    let _ = console::Term::stdout().clear_screen();
+                }
 
-    match jest_result {
-        Ok(jest_results) => {
-            if state.debug {
-                eprintln!("{}", style("成功解析为Jest测试结果格式").green());
+                // 获取 Sourcemap; 可能需要执行 rojo 命令, 不能阻塞异步运行时
+                let sourcemap = tokio::task::spawn_blocking({
+                    let state = state.clone();
+                    move || state.sourcemap.load(&state.config, state.debug)
+                })
+                .await
+                .ok()
+                .flatten();
+
+                let reported =
+                    report_results(&jest_results, &state.report_options, sourcemap.as_deref());
+
+                if state.watch {
+                    *state.last_failed_tests.lock().unwrap() =
+                        jest_results.results.failed_test_names();
+                }
+
+                let pass_with_no_tests = state
+                    .run_cli_options()
+                    .and_then(|options| options.pass_with_no_tests)
+                    .unwrap_or(false);
+                ExitCode::after_report(reported, &jest_results.results, pass_with_no_tests)
             }
+            Err(e) => diagnose_unparsed_results(&body, &e, state.debug),
+        }
+    };
 
-            // 获取 Sourcemap
-            let sourcemap: Option<RojoSourceMap> =
-                if let Some(command) = &state.config.rojo_source_map_command {
-                    if state.debug {
-                        eprintln!("Sourcemap command: {}", command);
-                    }
-                    // 尝试从配置文件所在的目录执行命令
-                    let config_dir = std::env::current_dir().ok().and_then(|curr_dir| {
-                        curr_dir
-                            .join("jest-lua-companion.config.json")
-                            .parent()
-                            .map(|p| p.to_path_buf())
-                    });
-
-                    match get_sourcemap(command, config_dir.as_deref().and_then(|p| p.to_str())) {
-                        Ok(sm) => {
-                            if state.debug {}
-                            Some(sm)
-                        }
-                        Err(e) => {
-                            eprintln!("{}", style(format!("获取Rojo Sourcemap失败: {}", e)).red());
-                            None
-                        }
-                    }
-                } else {
-                    if state.debug {
-                        eprintln!("{}", style("未配置Rojo Sourcemap命令").yellow());
-                    }
-                    None
-                };
-
-            let success = print_jest_test_results(
-                &jest_results,
-                state.only_log_failures,
-                state.debug,
-                sourcemap.as_ref(),
-            );
+    // 监听模式下不退出, 等待下一轮
+    if state.watch {
+        watch::print_watch_usage(&state);
+        return StatusCode::OK;
+    }
 
-            // 延迟退出，确保响应先发送
-            spawn(async move {
-                sleep(Duration::from_millis(100)).await;
-                exit(if success { 0 } else { 1 });
-            });
+    // 优雅关闭会等待本次响应发送完成
+    state.shutdown(exit_code);
+
+    StatusCode::OK
+}
+
+/// 输出插件加载或运行Jest失败的信息
+fn report_jest_load_failure(body: &Value) {
+    eprintln!("{}", style("插件无法加载或运行Jest").red().bold());
+
+    let errors = body
+        .pointer("/results/errors")
+        .and_then(Value::as_array)
+        .map(|errors| errors.iter().filter_map(Value::as_str).collect::<Vec<_>>())
+        .unwrap_or_default();
+
+    if errors.is_empty() {
+        eprintln!(
+            "{}",
+            style("Jest运行出错, 详细信息请查看Studio输出窗口").red()
+        );
+    } else {
+        for error in errors {
+            eprintln!("{}", style(error).red());
+        }
+    }
+
+    eprintln!("请检查:");
+    eprintln!(
+        "  1. ReplicatedStorage.rbxts_include.node_modules[\"@rbxts\"].jest 是否存在 (已安装 @rbxts/jest 并同步到Studio)"
+    );
+    eprintln!(
+        "  2. FFlag FFlagEnableLoadModule 是否为 true (可运行 `npx jest-lua-companion init`), 修改后需重启 Studio"
+    );
+}
+
+/// 无法解析为Jest结果时确定退出码, 并输出诊断信息(详细信息仅在调试模式下输出)
+///
+/// 退出码与是否开启调试模式无关: 结果中报告了运行时错误测试套件时为 `RuntimeErrors`, 否则为 `MalformedResults`
+fn diagnose_unparsed_results(body: &Value, e: &serde_json::Error, debug: bool) -> ExitCode {
+    let has_test_results = body
+        .pointer("/results/testResults")
+        .is_some_and(Value::is_array);
+    let runtime_errors = body
+        .pointer("/results/numRuntimeErrorTestSuites")
+        .and_then(Value::as_u64)
+        .unwrap_or(0);
+    // 运行时错误也应该被视为失败
+    let exit_code = if has_test_results && runtime_errors > 0 {
+        ExitCode::RuntimeErrors
+    } else {
+        ExitCode::MalformedResults
+    };
+
+    if !debug {
+        eprintln!("{}", style(format!("解析测试结果失败: {}", e)).red());
+        return exit_code;
+    }
+
+    eprintln!("{}", style(format!("解析Jest结果失败: {}", e)).red());
+    eprintln!("{}", style("尝试查看数据结构...").yellow());
+
+    // 输出接收到的数据的顶级字段，以便了解其结构
+    if let Some(obj) = body.as_object() {
+        eprintln!("{}", style("接收到的JSON对象包含以下顶级字段:").yellow());
+        for (key, value) in obj {
+            let type_info = match value {
+                Value::Null => "null".to_string(),
+                Value::Bool(_) => "布尔值".to_string(),
+                Value::Number(_) => "数字".to_string(),
+                Value::String(_) => "字符串".to_string(),
+                Value::Array(arr) => format!("数组({}项)", arr.len()),
+                Value::Object(obj) => format!("对象({}个字段)", obj.len()),
+            };
+            eprintln!("  - {}: {}", key, type_info);
         }
-        Err(e) => {
-            if state.debug {
-                eprintln!("{}", style(format!("解析Jest结果失败: {}", e)).red());
-                eprintln!("{}", style("尝试查看数据结构...").yellow());
-
-                // 输出接收到的数据的顶级字段，以便了解其结构
-                if let Some(obj) = body.as_object() {
-                    eprintln!("{}", style("接收到的JSON对象包含以下顶级字段:").yellow());
-                    for (key, value) in obj {
-                        let type_info = match value {
-                            Value::Null => "null".to_string(),
-                            Value::Bool(_) => "布尔值".to_string(),
-                            Value::Number(_) => "数字".to_string(),
-                            Value::String(_) => "字符串".to_string(),
-                            Value::Array(arr) => format!("数组({}项)", arr.len()),
-                            Value::Object(obj) => format!("对象({}个字段)", obj.len()),
-                        };
-                        eprintln!("  - {}: {}", key, type_info);
+    }
+
+    // 尝试直接处理原始数据，提取测试结果信息
+    if let Some(results) = body.get("results") {
+        if let Some(test_results) = results.get("testResults") {
+            if let Some(arr) = test_results.as_array() {
+                eprintln!(
+                    "{}",
+                    style(format!("找到测试结果数组，包含{}个测试文件", arr.len())).green()
+                );
+
+                // 输出简单的测试摘要
+                if let Some(passed) = results.get("numPassedTests") {
+                    if let Some(failed) = results.get("numFailedTests") {
+                        eprintln!(
+                            "测试摘要: 通过 {}, 失败 {}",
+                            passed.as_u64().unwrap_or(0),
+                            failed.as_u64().unwrap_or(0)
+                        );
                     }
                 }
 
-                // 尝试直接处理原始数据，提取测试结果信息
-                if let Some(results) = body.get("results") {
-                    if let Some(test_results) = results.get("testResults") {
-                        if let Some(arr) = test_results.as_array() {
-                            eprintln!(
-                                "{}",
-                                style(format!("找到测试结果数组，包含{}个测试文件", arr.len()))
-                                    .green()
-                            );
-
-                            // 输出简单的测试摘要
-                            if let Some(passed) = results.get("numPassedTests") {
-                                if let Some(failed) = results.get("numFailedTests") {
-                                    eprintln!(
-                                        "测试摘要: 通过 {}, 失败 {}",
-                                        passed.as_u64().unwrap_or(0),
-                                        failed.as_u64().unwrap_or(0)
-                                    );
-                                }
-                            }
-
-                            // 检查是否有运行时错误测试套件
-                            if let Some(runtime_errors) = results.get("numRuntimeErrorTestSuites") {
-                                let error_count = runtime_errors.as_u64().unwrap_or(0);
-                                if error_count > 0 {
-                                    eprintln!(
-                                        "{}",
-                                        style(format!("检测到{}个运行时错误测试套件", error_count))
-                                            .red()
-                                    );
-
-                                    // 输出运行时错误信息
-                                    for test_file in arr {
-                                        if let Some(failure_message) =
-                                            test_file.get("failureMessage")
-                                        {
-                                            if let Some(message) = failure_message.as_str() {
-                                                eprintln!(
-                                                    "{}",
-                                                    style("测试套件运行时错误:").red().bold()
-                                                );
-                                                eprintln!("{}", style(message).red());
-                                            }
-                                        }
-                                    }
-
-                                    // 运行时错误也应该被视为失败
-                                    spawn(async move {
-                                        sleep(Duration::from_millis(100)).await;
-                                        exit(1);
-                                    });
-                                    return StatusCode::OK;
+                // 检查是否有运行时错误测试套件
+                if let Some(runtime_errors) = results.get("numRuntimeErrorTestSuites") {
+                    let error_count = runtime_errors.as_u64().unwrap_or(0);
+                    if error_count > 0 {
+                        eprintln!(
+                            "{}",
+                            style(format!("检测到{}个运行时错误测试套件", error_count)).red()
+                        );
+
+                        // 输出运行时错误信息
+                        for test_file in arr {
+                            if let Some(failure_message) = test_file.get("failureMessage") {
+                                if let Some(message) = failure_message.as_str() {
+                                    eprintln!("{}", style("测试套件运行时错误:").red().bold());
+                                    eprintln!("{}", style(message).red());
                                 }
                             }
                         }
                     }
                 }
-
-                eprintln!("{}", style("尽管解析失败，但仍返回成功状态码").yellow());
-            } else {
-                eprintln!("{}", style("解析测试结果失败").red());
             }
         }
     }
 
-    StatusCode::OK
+    exit_code
 }
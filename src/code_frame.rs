@@ -0,0 +1,53 @@
+use console::style;
+use std::path::Path;
+
+/// 失败行之前显示的行数, 与 Jest 一致
+const LINES_ABOVE: usize = 2;
+/// 失败行之后显示的行数, 与 Jest 一致
+const LINES_BELOW: usize = 3;
+
+/// 读取本地文件, 生成 Jest 风格的代码帧: 失败行前后的代码、行号栏, 以及失败行下方的 `^` 标记
+///
+/// 堆栈中没有列号, `^` 指向失败行的第一个非空白字符. 文件无法读取或行号超出范围时返回 None
+pub fn render_code_frame(file_path: &Path, line: u32) -> Option<Vec<String>> {
+    let source = std::fs::read_to_string(file_path).ok()?;
+    let lines: Vec<&str> = source.lines().collect();
+
+    let line = line as usize;
+    if line == 0 || line > lines.len() {
+        return None;
+    }
+
+    let start = line.saturating_sub(LINES_ABOVE).max(1);
+    let end = (line + LINES_BELOW).min(lines.len());
+    let gutter_width = end.to_string().len();
+
+    let mut frame = Vec::new();
+    for number in start..=end {
+        let code = lines[number - 1].replace('\t', "  ");
+        if number == line {
+            frame.push(format!(
+                "{} {} {}",
+                style(">").red().bold(),
+                style(format!("{:>width$} |", number, width = gutter_width)).dim(),
+                code
+            ));
+
+            let indent = code.len() - code.trim_start().len();
+            frame.push(format!(
+                "  {} {}{}",
+                style(format!("{:>width$} |", "", width = gutter_width)).dim(),
+                " ".repeat(indent),
+                style("^").red().bold()
+            ));
+        } else {
+            frame.push(format!(
+                "  {} {}",
+                style(format!("{:>width$} |", number, width = gutter_width)).dim(),
+                code
+            ));
+        }
+    }
+
+    Some(frame)
+}
@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::collections::HashMap;
+use std::{collections::HashMap, fmt, path::PathBuf};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Config {
@@ -12,10 +12,76 @@ pub struct Config {
         rename = "rojoSourceMapCommand",
         skip_serializing_if = "Option::is_none"
     )]
-    pub rojo_source_map_command: Option<String>,
+    pub rojo_source_map_command: Option<SourceMapCommand>,
+    /// 执行 `rojoSourceMapCommand` 的工作目录, 默认为当前目录
+    #[serde(rename = "rojoSourceMapCwd", skip_serializing_if = "Option::is_none")]
+    pub rojo_source_map_cwd: Option<String>,
+    /// `rojoSourceMapCommand` 的超时秒数, 默认 30
+    #[serde(
+        rename = "rojoSourceMapTimeout",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub rojo_source_map_timeout: Option<u64>,
+    /// 未配置 `rojoSourceMapCommand` 时用于生成 Sourcemap 的 Rojo 项目文件, 默认 `default.project.json`
+    #[serde(rename = "rojoProjectFile", skip_serializing_if = "Option::is_none")]
+    pub rojo_project_file: Option<String>,
+    /// 堆栈中视为框架内部的帧的 DataModel 路径正则, 追加到默认规则(node_modules、Wally 包等)之后
+    #[serde(
+        rename = "stackIgnorePatterns",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub stack_ignore_patterns: Option<Vec<String>>,
+    /// 终端中堆栈位置的超链接目标, 如 `vscode://file/{abs}:{line}`; 未配置时链接到本地文件
+    #[serde(rename = "editorUrl", skip_serializing_if = "Option::is_none")]
+    pub editor_url: Option<String>,
+    /// 报告器列表, 格式同 `--reporter name[=path]`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reporters: Option<Vec<String>>,
+    /// 监听模式下要监听的文件 glob, 未配置时监听 Sourcemap 引用的目录
+    #[serde(rename = "watchPaths", skip_serializing_if = "Option::is_none")]
+    pub watch_paths: Option<Vec<String>>,
+    /// HTTP服务器监听地址, 默认 127.0.0.1
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    /// HTTP服务器端口, 默认 28860
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    /// 等待 Studio 连接的秒数, 默认一直等待
+    #[serde(rename = "connectTimeout", skip_serializing_if = "Option::is_none")]
+    pub connect_timeout: Option<u64>,
+    /// 下发测试后等待结果的秒数, 默认一直等待
+    #[serde(rename = "runTimeout", skip_serializing_if = "Option::is_none")]
+    pub run_timeout: Option<u64>,
+}
+
+impl Config {
+    /// Sourcemap 中的文件路径相对于此目录: rojo 命令输出的路径相对于命令的工作目录
+    pub fn sourcemap_base_dir(&self) -> PathBuf {
+        match (&self.rojo_source_map_command, &self.rojo_source_map_cwd) {
+            (Some(_), Some(cwd)) => PathBuf::from(cwd),
+            _ => PathBuf::new(),
+        }
+    }
 }
 
+/// 生成 Sourcemap 的命令: 字符串通过系统 shell 执行, 数组作为参数列表直接执行
 #[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum SourceMapCommand {
+    Shell(String),
+    Args(Vec<String>),
+}
+
+impl fmt::Display for SourceMapCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SourceMapCommand::Shell(command) => write!(f, "{}", command),
+            SourceMapCommand::Args(args) => write!(f, "{:?}", args),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct RunCliOptions {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub ci: Option<bool>,
@@ -0,0 +1,177 @@
+use console::style;
+use serde_json::Value;
+
+/// 超过此行数时不计算逐行差异, 避免 O(n*m) 的开销
+const MAX_DIFF_LINES: usize = 1000;
+
+#[derive(Debug, PartialEq, Eq)]
+enum DiffLine<'a> {
+    Equal(&'a str),
+    Expected(&'a str),
+    Received(&'a str),
+}
+
+/// `matcherResult` 中的期望值和实际值为对象、数组或多行字符串时, 生成 Jest 风格的 `- Expected / + Received` 逐行差异
+///
+/// 两者都是标量时返回 None, 由调用方按 `Expected: x` / `Received: y` 输出
+pub fn diff_values(expected: &Value, received: &Value) -> Option<Vec<String>> {
+    if !is_diffable(expected) && !is_diffable(received) {
+        return None;
+    }
+
+    let expected = to_text(expected);
+    let received = to_text(received);
+    Some(diff_lines(&expected, &received))
+}
+
+fn is_diffable(value: &Value) -> bool {
+    match value {
+        Value::Object(_) | Value::Array(_) => true,
+        Value::String(text) => text.contains('\n'),
+        _ => false,
+    }
+}
+
+/// 字符串按原样逐行比较, 其他值格式化为缩进的 JSON
+fn to_text(value: &Value) -> String {
+    match value {
+        Value::String(text) if text.contains('\n') => text.clone(),
+        value => serde_json::to_string_pretty(value).unwrap_or_else(|_| value.to_string()),
+    }
+}
+
+/// 逐行比较两段文本, 期望值为绿色 `-`, 实际值为红色 `+`
+fn diff_lines(expected: &str, received: &str) -> Vec<String> {
+    let diff = diff_text(expected, received);
+
+    let removed = diff
+        .iter()
+        .filter(|line| matches!(line, DiffLine::Expected(_)))
+        .count();
+    let added = diff
+        .iter()
+        .filter(|line| matches!(line, DiffLine::Received(_)))
+        .count();
+
+    let mut output = vec![
+        style(format!("- Expected  - {}", removed))
+            .green()
+            .to_string(),
+        style(format!("+ Received  + {}", added)).red().to_string(),
+        String::new(),
+    ];
+    output.extend(diff.iter().map(|line| match line {
+        DiffLine::Equal(line) => style(format!("  {}", line)).dim().to_string(),
+        DiffLine::Expected(line) => style(format!("- {}", line)).green().to_string(),
+        DiffLine::Received(line) => style(format!("+ {}", line)).red().to_string(),
+    }));
+    output
+}
+
+/// 按 `\n` 拆分后逐行比较, 末尾换行的差异表现为一个空行
+fn diff_text<'a>(expected: &'a str, received: &'a str) -> Vec<DiffLine<'a>> {
+    let expected: Vec<&str> = expected.split('\n').collect();
+    let received: Vec<&str> = received.split('\n').collect();
+
+    if expected.len() > MAX_DIFF_LINES || received.len() > MAX_DIFF_LINES {
+        // 太大时不对齐, 整体列出两者
+        return expected
+            .iter()
+            .map(|line| DiffLine::Expected(line))
+            .chain(received.iter().map(|line| DiffLine::Received(line)))
+            .collect();
+    }
+    line_diff(&expected, &received)
+}
+
+/// 基于最长公共子序列的逐行差异
+fn line_diff<'a>(expected: &[&'a str], received: &[&'a str]) -> Vec<DiffLine<'a>> {
+    let (n, m) = (expected.len(), received.len());
+
+    // lcs[i][j]: expected[i..] 与 received[j..] 的最长公共子序列长度
+    let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if expected[i] == received[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut diff = Vec::with_capacity(n + m);
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if expected[i] == received[j] {
+            diff.push(DiffLine::Equal(expected[i]));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            diff.push(DiffLine::Expected(expected[i]));
+            i += 1;
+        } else {
+            diff.push(DiffLine::Received(received[j]));
+            j += 1;
+        }
+    }
+    diff.extend(expected[i..].iter().map(|line| DiffLine::Expected(line)));
+    diff.extend(received[j..].iter().map(|line| DiffLine::Received(line)));
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use DiffLine::{Equal, Expected, Received};
+
+    #[test]
+    fn equal_text_has_no_changes() {
+        assert_eq!(
+            diff_text("a\nb\nc", "a\nb\nc"),
+            [Equal("a"), Equal("b"), Equal("c")]
+        );
+    }
+
+    #[test]
+    fn inserted_lines_are_received() {
+        assert_eq!(
+            diff_text("a\nc", "a\nb\nc\nd"),
+            [Equal("a"), Received("b"), Equal("c"), Received("d")]
+        );
+    }
+
+    #[test]
+    fn deleted_lines_are_expected() {
+        assert_eq!(
+            diff_text("a\nb\nc\nd", "b\nd"),
+            [Expected("a"), Equal("b"), Expected("c"), Equal("d")]
+        );
+    }
+
+    #[test]
+    fn changed_lines_list_expected_before_received() {
+        assert_eq!(
+            diff_text("a\nb\nc", "a\nx\nc"),
+            [Equal("a"), Expected("b"), Received("x"), Equal("c")]
+        );
+    }
+
+    #[test]
+    fn trailing_newline_difference_is_shown() {
+        assert_eq!(
+            diff_text("a\nb\n", "a\nb"),
+            [Equal("a"), Equal("b"), Expected("")]
+        );
+        assert_eq!(
+            diff_text("a\nb", "a\nb\n"),
+            [Equal("a"), Equal("b"), Received("")]
+        );
+    }
+
+    #[test]
+    fn scalars_are_not_diffed() {
+        assert_eq!(diff_values(&Value::from(1), &Value::from("1")), None);
+        assert!(diff_values(&Value::from("a\nb"), &Value::from("a\nc")).is_some());
+    }
+}
@@ -0,0 +1,64 @@
+use crate::jest_results::Results;
+use std::io;
+
+/// 进程退出码, 供CI脚本区分基础设施问题和真正的测试失败
+///
+/// 数值一经发布不再改变, 新的类别只追加. 2 保留给命令行参数错误(由 clap 使用)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitCode {
+    /// 所有测试通过
+    Success = 0,
+    /// 存在失败的测试
+    TestFailures = 1,
+    /// 存在运行时错误的测试套件 (`numRuntimeErrorTestSuites` > 0)
+    RuntimeErrors = 3,
+    /// 插件无法加载或运行 Jest (`caught-jest-error` 请求头)
+    JestLoadFailure = 4,
+    /// 没有找到任何测试, 且未设置 `passWithNoTests`
+    NoTests = 5,
+    /// 收到的测试结果无法解析
+    MalformedResults = 6,
+    /// `--connect-timeout` 内没有任何 Studio 插件连接, 或 `--place-timeout` 内没有匹配的place
+    ConnectTimeout = 7,
+    /// 测试下发后 `--run-timeout` 内没有收到结果
+    RunTimeout = 8,
+    /// 测试运行被中断 (`wasInterrupted`), 用户取消了交互提示, 或按下了 Ctrl+C
+    Interrupted = 9,
+    /// 配置文件、命令行参数组合、报告文件写入或监听端口等本地环境错误
+    ConfigError = 10,
+}
+
+impl ExitCode {
+    /// 根据测试结果确定退出码, 按中断、运行时错误、测试失败、没有测试的顺序判断
+    pub fn from_results(results: &Results, pass_with_no_tests: bool) -> Self {
+        if results.was_interrupted {
+            ExitCode::Interrupted
+        } else if results.num_runtime_error_test_suites > 0 {
+            ExitCode::RuntimeErrors
+        } else if !results.is_success() {
+            ExitCode::TestFailures
+        } else if results.num_total_tests == 0 && !pass_with_no_tests {
+            ExitCode::NoTests
+        } else {
+            ExitCode::Success
+        }
+    }
+
+    /// 输出报告之后的退出码: 报告文件写入失败时 CI 拿不到结果, 不能以测试结果的退出码结束
+    pub fn after_report(
+        reported: io::Result<()>,
+        results: &Results,
+        pass_with_no_tests: bool,
+    ) -> Self {
+        match reported {
+            Ok(()) => ExitCode::from_results(results, pass_with_no_tests),
+            Err(_) => ExitCode::ConfigError,
+        }
+    }
+}
+
+impl From<ExitCode> for std::process::ExitCode {
+    fn from(code: ExitCode) -> Self {
+        std::process::ExitCode::from(code as u8)
+    }
+}
@@ -0,0 +1,162 @@
+use std::{
+    io::IsTerminal,
+    path::{Path, PathBuf},
+};
+
+use crate::config::Config;
+
+/// 堆栈中本地文件位置的显示方式: 相对于项目根目录的路径, 终端支持时带可点击的 OSC 8 超链接
+#[derive(Debug, Clone)]
+pub struct FileLinks {
+    /// 项目根目录(当前目录), 显示路径相对于此目录
+    project_root: PathBuf,
+    /// Sourcemap 中的路径相对于此目录, 配置了 `rojoSourceMapCwd` 时与项目根目录不同
+    source_root: PathBuf,
+    /// 超链接目标模板, 支持 `{abs}` 和 `{line}`; 未配置时链接到 `file://` URL
+    editor_url: Option<String>,
+    hyperlinks: bool,
+}
+
+impl FileLinks {
+    pub fn new(config: &Config, hyperlinks: bool) -> Self {
+        let project_root = std::env::current_dir()
+            .map(|cwd| canonicalize(&cwd))
+            .unwrap_or_default();
+        let source_root = match (&config.rojo_source_map_command, &config.rojo_source_map_cwd) {
+            (Some(_), Some(cwd)) => canonicalize(&project_root.join(cwd)),
+            _ => project_root.clone(),
+        };
+
+        FileLinks {
+            project_root,
+            source_root,
+            editor_url: config.editor_url.clone(),
+            hyperlinks,
+        }
+    }
+
+    /// 不带超链接的副本, 用于写入文件的报告器
+    pub fn plain(&self) -> Self {
+        FileLinks {
+            hyperlinks: false,
+            ..self.clone()
+        }
+    }
+
+    /// Sourcemap 中的文件路径对应的绝对路径
+    pub fn absolute(&self, file_path: &str) -> PathBuf {
+        canonicalize(&self.source_root.join(file_path))
+    }
+
+    /// 相对于项目根目录的路径, 在项目之外时为绝对路径
+    pub fn display(&self, file_path: &str) -> String {
+        let absolute = self.absolute(file_path);
+        let path = absolute
+            .strip_prefix(&self.project_root)
+            .unwrap_or(&absolute);
+        path_string(path)
+    }
+
+    /// 相对于指定目录的路径, 在该目录之外时为绝对路径
+    pub fn display_relative_to(&self, file_path: &str, root: &Path) -> String {
+        let absolute = self.absolute(file_path);
+        let root = canonicalize(root);
+        path_string(absolute.strip_prefix(&root).unwrap_or(&absolute))
+    }
+
+    /// `path:line`, 启用超链接时点击可在编辑器中打开对应行
+    pub fn location(&self, file_path: &str, line: u32) -> String {
+        let text = format!("{}:{}", self.display(file_path), line);
+        if !self.hyperlinks {
+            return text;
+        }
+
+        let url = match &self.editor_url {
+            Some(template) => template
+                .replace("{abs}", &url_path(&self.absolute(file_path)))
+                .replace("{line}", &line.to_string()),
+            None => self.file_url(file_path),
+        };
+        format!("\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", url, text)
+    }
+
+    /// 文件的 `file://` URL
+    pub fn file_url(&self, file_path: &str) -> String {
+        to_file_url(&self.absolute(file_path))
+    }
+
+    /// 项目根目录的 `file://` URL, 以 `/` 结尾
+    pub fn project_root_url(&self) -> String {
+        let url = to_file_url(&self.project_root);
+        if url.ends_with('/') {
+            url
+        } else {
+            url + "/"
+        }
+    }
+}
+
+/// 检测 stdout 所在的终端是否支持 OSC 8 超链接
+///
+/// 可以通过环境变量 `FORCE_HYPERLINK=1`/`0` 强制开启或关闭
+pub fn supports_hyperlinks() -> bool {
+    if let Ok(value) = std::env::var("FORCE_HYPERLINK") {
+        return value != "0";
+    }
+    if !std::io::stdout().is_terminal()
+        || !console::colors_enabled()
+        || std::env::var_os("CI").is_some()
+    {
+        return false;
+    }
+
+    let env = |name: &str| std::env::var(name).unwrap_or_default();
+    if std::env::var_os("WT_SESSION").is_some() || std::env::var_os("KONSOLE_VERSION").is_some() {
+        return true;
+    }
+    if env("VTE_VERSION")
+        .parse::<u32>()
+        .is_ok_and(|version| version >= 5000)
+    {
+        return true;
+    }
+    if matches!(
+        env("TERM_PROGRAM").as_str(),
+        "iTerm.app" | "WezTerm" | "vscode" | "ghostty" | "Hyper"
+    ) {
+        return true;
+    }
+    let term = env("TERM");
+    ["kitty", "alacritty", "foot", "ghostty", "wezterm"]
+        .iter()
+        .any(|name| term.contains(name))
+}
+
+/// 规范化路径; 文件不存在时原样返回. 去掉 Windows 上的 `\\?\` 前缀, 以便生成 URL
+fn canonicalize(path: &Path) -> PathBuf {
+    match std::fs::canonicalize(path) {
+        Ok(path) => {
+            let path = path.to_string_lossy();
+            PathBuf::from(path.strip_prefix(r"\\?\").unwrap_or(&path))
+        }
+        Err(_) => path.to_path_buf(),
+    }
+}
+
+fn to_file_url(path: &Path) -> String {
+    let path = url_path(path);
+    if path.starts_with('/') {
+        format!("file://{}", path)
+    } else {
+        format!("file:///{}", path)
+    }
+}
+
+/// 用于 URL 的路径: `/` 分隔, 空格转义
+fn url_path(path: &Path) -> String {
+    path_string(path).replace(' ', "%20")
+}
+
+fn path_string(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
@@ -5,14 +5,39 @@ use axum::{
 use clap::Parser;
 use config::{Config, RunCliOptions};
 use console::style;
-use std::{net::SocketAddr, sync::Arc};
+use exit_code::ExitCode;
+use file_links::FileLinks;
+use places::PlaceFilter;
+use reporters::{ReportOptions, ReporterKind, ReporterSpec};
+use sourcemap_cache::SourcemapCache;
+use stack_trace::FrameFilter;
+use std::{
+    io::Write,
+    path::PathBuf,
+    sync::{atomic::AtomicBool, Arc, Mutex},
+    time::Duration,
+};
 use tokio::fs::read_to_string;
 
 mod api;
+mod code_frame;
 mod config;
+mod diff;
+mod exit_code;
+mod file_links;
 mod jest_results;
+mod places;
+mod replay;
+mod reporters;
+mod rojo_project;
 mod rojo_sourcemap;
+mod server;
+mod sourcemap_cache;
+mod sourcemap_trie;
+mod stack_trace;
 mod state;
+mod timeouts;
+mod watch;
 
 #[derive(Parser)]
 struct Cli {
@@ -28,6 +53,62 @@ struct Cli {
     #[arg(short, long, default_value = "false")]
     pub debug: bool,
 
+    /// 测试结果报告器, 格式为 name[=path], 可重复指定 (console, junit, json, github-actions, sarif, tap)
+    #[arg(long = "reporter", value_name = "NAME[=PATH]")]
+    pub reporters: Vec<ReporterSpec>,
+
+    /// 监听模式: 本地文件变更后自动重新运行测试
+    #[arg(long)]
+    pub watch: bool,
+
+    /// HTTP服务器监听地址
+    #[arg(long)]
+    pub host: Option<String>,
+
+    /// HTTP服务器端口, 被占用时会依次尝试之后的端口
+    #[arg(long)]
+    pub port: Option<u16>,
+
+    /// 等待 Studio 插件连接的秒数, 超时后以退出码 7 退出
+    #[arg(long)]
+    pub connect_timeout: Option<u64>,
+
+    /// 下发测试后等待结果的秒数, 超时后以退出码 8 退出
+    #[arg(long)]
+    pub run_timeout: Option<u64>,
+
+    /// 输出完整堆栈, 不折叠 Jest 及依赖包内部的堆栈帧
+    #[arg(long)]
+    pub full_stack: bool,
+
+    /// 不使用 Sourcemap 缓存, 强制重新生成
+    #[arg(long)]
+    pub no_sourcemap_cache: bool,
+
+    /// 只在指定 PlaceId 的place中运行测试
+    #[arg(long)]
+    pub place_id: Option<u64>,
+
+    /// 只在名称匹配 glob 的place中运行测试
+    #[arg(long)]
+    pub place_name: Option<glob::Pattern>,
+
+    /// 只在指定 GUID 的place中运行测试
+    #[arg(long)]
+    pub place_guid: Option<String>,
+
+    /// 指定 --place-* 时, 等待匹配place连接的秒数
+    #[arg(long, default_value = "30")]
+    pub place_timeout: u64,
+
+    /// 文件类报告器未指定路径时, 默认文件名(junit.xml 等)写入此目录
+    #[arg(long)]
+    pub reports_dir: Option<PathBuf>,
+
+    /// 与 Jest 相同: json 报告器的输出文件, 只对 json 报告器生效
+    #[arg(long = "outputFile")]
+    pub json_output_file: Option<String>,
+
     // Jest测试选项
     #[arg(long)]
     pub ci: Option<bool>,
@@ -41,7 +122,8 @@ struct Cli {
     #[arg(long)]
     pub expand: Option<bool>,
 
-    #[arg(long)]
+    /// 同时转发给 Jest, 并额外使用 json 报告器输出 `jest --json` 格式的结果
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
     pub json: Option<bool>,
 
     #[arg(long = "listTests")]
@@ -88,7 +170,7 @@ struct Cli {
 }
 
 #[tokio::main]
-async fn main() {
+async fn main() -> std::process::ExitCode {
     let cli = Cli::parse();
 
     // 默认启动服务器模式
@@ -130,7 +212,7 @@ async fn main() {
                                 .bold()
                         );
                         eprintln!("{}", style("配置文件格式错误，程序退出").red().bold());
-                        std::process::exit(1);
+                        return ExitCode::ConfigError.into();
                     }
                 }
             }
@@ -140,7 +222,7 @@ async fn main() {
                     style(format!("无法读取JSON配置文件: {}", e)).red().bold()
                 );
                 eprintln!("{}", style("配置文件不存在，程序退出").red().bold());
-                std::process::exit(1);
+                return ExitCode::ConfigError.into();
             }
         };
 
@@ -178,46 +260,233 @@ async fn main() {
         Arc::new(config)
     };
 
+    let reporters = match resolve_reporters(&cli, &config) {
+        Ok(reporters) => reporters,
+        Err(exit_code) => return exit_code.into(),
+    };
+    let frame_filter = match FrameFilter::new(
+        config.stack_ignore_patterns.as_deref().unwrap_or_default(),
+        !cli.full_stack,
+    ) {
+        Ok(frame_filter) => frame_filter,
+        Err(e) => {
+            eprintln!(
+                "{}",
+                style(format!("配置文件中的 stackIgnorePatterns 无效: {}", e))
+                    .red()
+                    .bold()
+            );
+            return ExitCode::ConfigError.into();
+        }
+    };
+    let report_options = ReportOptions {
+        reporters,
+        reports_dir: cli.reports_dir.clone(),
+        frame_filter,
+        file_links: FileLinks::new(&config, file_links::supports_hyperlinks()),
+        only_failures: cli.only_print_failures,
+        debug: cli.debug,
+    };
+    if let Err(e) = report_options.check_output_files() {
+        eprintln!("{}", style(e).red().bold());
+        return ExitCode::ConfigError.into();
+    }
+
+    let sourcemap_cache = SourcemapCache::new(!cli.no_sourcemap_cache);
+
+    // 离线回放模式: 直接从文件加载测试结果, 不启动HTTP服务器
+    if cli.jest_results_file.is_some() || cli.use_rsp_json {
+        let path = cli
+            .jest_results_file
+            .as_deref()
+            .unwrap_or(replay::RSP_JSON_FILE);
+
+        return replay::replay_results_file(path, &config, &report_options, &sourcemap_cache)
+            .into();
+    }
+
+    let (shutdown_tx, shutdown_rx) = tokio::sync::mpsc::unbounded_channel();
     let state = Arc::new(state::AppState {
-        config,
+        config: config.clone(),
         places: dashmap::DashMap::new(),
         active_place: tokio::sync::Mutex::new(None),
-        only_log_failures: cli.only_print_failures,
+        place_filter: PlaceFilter {
+            id: cli.place_id,
+            name: cli.place_name.clone(),
+            guid: cli.place_guid.clone(),
+        },
+        place_timeout: Duration::from_secs(cli.place_timeout),
+        run_pending: AtomicBool::new(true),
+        dispatched_at: Mutex::new(None),
+        connect_timeout: cli
+            .connect_timeout
+            .or(config.connect_timeout)
+            .map(Duration::from_secs),
+        run_timeout: cli
+            .run_timeout
+            .or(config.run_timeout)
+            .map(Duration::from_secs),
+        run_cli_options: Mutex::new(config.run_cli_options.clone()),
+        update_snapshot_once: AtomicBool::new(false),
+        last_failed_tests: Mutex::new(Vec::new()),
+        key_handler_active: AtomicBool::new(false),
+        sourcemap: sourcemap_cache,
+        report_options,
         debug: cli.debug,
+        watch: cli.watch,
+        shutdown: shutdown_tx,
     });
 
-    if cli.jest_results_file.is_some() || cli.use_rsp_json {
+    // 按键监听会把终端切换到原始模式, 退出时恢复
+    let _terminal_guard = cli.watch.then(watch::TerminalGuard::save);
+
+    if cli.watch {
+        watch::spawn_watcher(state.clone());
+    }
+
+    // 选定活跃place后才会下发测试; 按键监听在选择结束后启动, 避免与选择提示争用终端
+    tokio::spawn({
+        let state = state.clone();
+        async move {
+            if places::select_active_place(state.clone()).await && state.watch {
+                watch::spawn_key_handler(state);
+            }
+        }
+    });
+
+    eprintln!("{}", style("启动HTTP服务器等待测试结果...").green());
+
+    // 只在调试模式下输出提示信息
+    if cli.debug {
         eprintln!(
             "{}",
-            style("未找到指定的测试结果文件，启动HTTP服务器等待测试结果...").yellow()
+            style("提示：可以使用 --jest-results-file 参数从文件加载测试结果").dim()
         );
-    } else {
-        eprintln!("{}", style("启动HTTP服务器等待测试结果...").green());
+        eprintln!(
+            "{}",
+            style("提示：可以使用 --use-rsp-json 参数从rsp.json文件加载测试结果").dim()
+        );
+    }
 
-        // 只在调试模式下输出提示信息
-        if cli.debug {
+    let app = Router::new()
+        .route("/poll", get(api::poll))
+        .route("/logs", post(api::logs))
+        .route("/results", post(api::results))
+        .with_state(state.clone());
+
+    let host = cli
+        .host
+        .clone()
+        .or_else(|| config.host.clone())
+        .unwrap_or_else(|| server::DEFAULT_HOST.to_string());
+    let port = cli.port.or(config.port).unwrap_or(server::DEFAULT_PORT);
+
+    let listener = match server::bind_listener(&host, port) {
+        Ok(listener) => listener,
+        Err(e) => {
             eprintln!(
                 "{}",
-                style("提示：可以使用 --jest-results-file 参数从文件加载测试结果").dim()
+                style(format!("无法监听 {}:{}: {}", host, port, e))
+                    .red()
+                    .bold()
             );
+            return ExitCode::ConfigError.into();
+        }
+    };
+    let addr = listener.local_addr().unwrap();
+
+    match server::write_discovery_file(addr) {
+        Ok(path) => {
+            if cli.debug {
+                eprintln!(
+                    "{}",
+                    style(format!("已写入发现文件: {}", path.display())).dim()
+                );
+            }
+        }
+        Err(e) => {
+            eprintln!("{}", style(format!("写入发现文件失败: {}", e)).yellow());
+        }
+    }
+
+    eprintln!("{}", style(format!("监听地址: http://{}", addr)).dim());
+
+    tokio::spawn(timeouts::monitor_timeouts(state.clone(), addr));
+
+    let exit_code = match server::serve(app, listener, shutdown_rx).await {
+        Ok(exit_code) => exit_code,
+        Err(e) => {
+            eprintln!("{}", style(format!("HTTP服务器出错: {}", e)).red().bold());
+            ExitCode::ConfigError
+        }
+    };
+
+    if let Err(e) = server::remove_discovery_file(addr.port()) {
+        eprintln!("{}", style(format!("删除发现文件失败: {}", e)).yellow());
+    }
+    let _ = std::io::stdout().flush();
+
+    exit_code.into()
+}
+
+// 确定要使用的报告器: 命令行优先, 其次是配置文件, 默认为 console
+// 命令行中的 --json 追加 json 报告器(配置文件中的 runCLI-options.json 只转发给 Jest),
+// --outputFile 指定 json 报告器的输出文件
+fn resolve_reporters(cli: &Cli, config: &Config) -> Result<Vec<ReporterSpec>, ExitCode> {
+    let mut reporters = configured_reporters(cli, config)?;
+
+    if cli.json == Some(true) && !reporters.iter().any(|spec| spec.kind == ReporterKind::Json) {
+        reporters.push(ReporterSpec {
+            kind: ReporterKind::Json,
+            output_file: None,
+        });
+    }
+
+    if let Some(json_output_file) = &cli.json_output_file {
+        let mut json_reporters = reporters
+            .iter_mut()
+            .filter(|spec| spec.kind == ReporterKind::Json)
+            .peekable();
+        if json_reporters.peek().is_none() {
             eprintln!(
                 "{}",
-                style("提示：可以使用 --use-rsp-json 参数从rsp.json文件加载测试结果").dim()
+                style("--outputFile 只在启用 json 报告器(--json 或 --reporter json)时生效")
+                    .yellow()
             );
         }
+        for spec in json_reporters.filter(|spec| spec.output_file.is_none()) {
+            spec.output_file = Some(json_output_file.clone());
+        }
     }
 
-    let app = Router::new()
-        .route("/poll", get(api::poll))
-        .route("/logs", post(api::logs))
-        .route("/results", post(api::results))
-        .with_state(state);
+    Ok(reporters)
+}
 
-    let addr = SocketAddr::from(([127, 0, 0, 1], 28860));
-    axum_server::bind(addr)
-        .serve(app.into_make_service())
-        .await
-        .unwrap();
+fn configured_reporters(cli: &Cli, config: &Config) -> Result<Vec<ReporterSpec>, ExitCode> {
+    if !cli.reporters.is_empty() {
+        return Ok(cli.reporters.clone());
+    }
+
+    match &config.reporters {
+        Some(names) => names
+            .iter()
+            .map(|name| {
+                name.parse::<ReporterSpec>().map_err(|e| {
+                    eprintln!(
+                        "{}",
+                        style(format!("配置文件中的 reporters 无效: {}", e))
+                            .red()
+                            .bold()
+                    );
+                    ExitCode::ConfigError
+                })
+            })
+            .collect(),
+        None => Ok(vec![ReporterSpec {
+            kind: ReporterKind::Console,
+            output_file: None,
+        }]),
+    }
 }
 
 // 从命令行参数创建RunCliOptions
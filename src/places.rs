@@ -0,0 +1,227 @@
+use crate::{
+    exit_code::ExitCode,
+    state::{AppState, Place},
+};
+use console::style;
+use inquire::InquireError;
+use std::{fmt, io::IsTerminal, sync::Arc, time::Duration};
+use tokio::time::{sleep, Instant};
+
+/// 第一个place出现后, 继续等待其他place轮询的时间; 未被选中的插件会轮流轮询各个端口, 需要覆盖一轮扫描
+const PLACE_COLLECT_WINDOW: Duration = Duration::from_millis(3000);
+/// 等待place轮询时的检查间隔
+const PLACE_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 通过命令行参数指定目标place, 所有已指定的条件都需满足
+#[derive(Debug, Default)]
+pub struct PlaceFilter {
+    pub id: Option<u64>,
+    pub name: Option<glob::Pattern>,
+    pub guid: Option<String>,
+}
+
+impl PlaceFilter {
+    pub fn is_empty(&self) -> bool {
+        self.id.is_none() && self.name.is_none() && self.guid.is_none()
+    }
+
+    pub fn matches(&self, guid: &str, place: &Place) -> bool {
+        self.id.is_none_or(|id| id == place.id)
+            && self
+                .name
+                .as_ref()
+                .is_none_or(|pattern| pattern.matches(&place.name))
+            && self
+                .guid
+                .as_deref()
+                .is_none_or(|expected| expected.eq_ignore_ascii_case(guid))
+    }
+}
+
+impl fmt::Display for PlaceFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut conditions = Vec::new();
+        if let Some(id) = self.id {
+            conditions.push(format!("--place-id {}", id));
+        }
+        if let Some(name) = &self.name {
+            conditions.push(format!("--place-name {}", name));
+        }
+        if let Some(guid) = &self.guid {
+            conditions.push(format!("--place-guid {}", guid));
+        }
+        write!(f, "{}", conditions.join(" "))
+    }
+}
+
+/// 供选择的place
+struct PlaceOption {
+    guid: String,
+    place: Place,
+}
+
+impl fmt::Display for PlaceOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} (id: {}, guid: {})",
+            self.place.name, self.place.id, self.guid
+        )
+    }
+}
+
+/// 启动阶段: 收集正在轮询的place, 有多个place时提示用户选择, 选定后才会下发测试
+///
+/// 指定了 `--place-*` 参数时, 由 `/poll` 直接激活匹配的place, 这里只检查是否超时
+///
+/// 没有选定place时(超时或取消选择)会请求关闭服务器并返回 `false`
+pub async fn select_active_place(state: Arc<AppState>) -> bool {
+    if !state.place_filter.is_empty() {
+        return wait_for_matching_place(&state).await;
+    }
+
+    while state.places.is_empty() {
+        sleep(PLACE_CHECK_INTERVAL).await;
+    }
+    sleep(PLACE_COLLECT_WINDOW).await;
+
+    let mut options: Vec<PlaceOption> = state
+        .places
+        .iter()
+        .map(|entry| PlaceOption {
+            guid: entry.key().clone(),
+            place: entry.value().clone(),
+        })
+        .collect();
+    options.sort_by(|a, b| {
+        a.place
+            .name
+            .cmp(&b.place.name)
+            .then(a.place.id.cmp(&b.place.id))
+    });
+
+    let selected = if options.len() == 1 {
+        options.remove(0)
+    } else if let Some(index) = find_project_place(&state, &options).await {
+        options.remove(index)
+    } else {
+        match prompt_place(options).await {
+            Ok(selected) => selected,
+            Err(exit_code) => {
+                state.shutdown(exit_code);
+                return false;
+            }
+        }
+    };
+
+    eprintln!(
+        "{}",
+        style(format!(
+            "在place {} ({}) 中运行测试",
+            selected.place.name, selected.place.id
+        ))
+        .green()
+    );
+    state.active_place.lock().await.replace(selected.guid);
+    true
+}
+
+/// 等待与 `--place-*` 参数匹配的place, 超时后列出已连接的place并请求关闭服务器
+async fn wait_for_matching_place(state: &AppState) -> bool {
+    let deadline = Instant::now() + state.place_timeout;
+    while Instant::now() < deadline {
+        if state.active_place.lock().await.is_some() {
+            return true;
+        }
+        sleep(PLACE_CHECK_INTERVAL).await;
+    }
+
+    eprintln!(
+        "{}",
+        style(format!(
+            "{}秒内没有与 {} 匹配的place连接",
+            state.place_timeout.as_secs(),
+            state.place_filter
+        ))
+        .red()
+        .bold()
+    );
+    if state.places.is_empty() {
+        eprintln!(
+            "{}",
+            style("没有任何place连接, 请确认Studio已打开且插件已安装").yellow()
+        );
+    } else {
+        eprintln!("已连接的place:");
+        for entry in state.places.iter() {
+            eprintln!(
+                "  {} (id: {}, guid: {})",
+                entry.value().name,
+                entry.value().id,
+                entry.key()
+            );
+        }
+    }
+    state.shutdown(ExitCode::ConnectTimeout);
+    false
+}
+
+/// 多个place中只有一个与 rojo 项目名同名时, 自动选择它
+async fn find_project_place(state: &Arc<AppState>, options: &[PlaceOption]) -> Option<usize> {
+    let sourcemap = tokio::task::spawn_blocking({
+        let state = state.clone();
+        move || state.sourcemap.load(&state.config, state.debug)
+    })
+    .await
+    .ok()??;
+    let project_name = sourcemap.project_name()?;
+
+    let mut matching = options
+        .iter()
+        .enumerate()
+        .filter(|(_, option)| option.place.name == project_name);
+    let (index, _) = matching.next()?;
+    if matching.next().is_some() {
+        return None;
+    }
+
+    if state.debug {
+        eprintln!(
+            "{}",
+            style(format!("按rojo项目名 {} 自动选择place", project_name)).dim()
+        );
+    }
+    Some(index)
+}
+
+/// 提示用户从多个place中选择一个; 无法交互时使用第一个, 取消选择时返回对应的退出码
+async fn prompt_place(options: Vec<PlaceOption>) -> Result<PlaceOption, ExitCode> {
+    if !std::io::stdin().is_terminal() {
+        eprintln!(
+            "{}",
+            style(format!(
+                "检测到{}个已打开的place, 当前不是交互式终端, 使用第一个",
+                options.len()
+            ))
+            .yellow()
+        );
+        return Ok(options.into_iter().next().unwrap());
+    }
+
+    tokio::task::spawn_blocking(move || {
+        let prompt =
+            inquire::Select::new("检测到多个已打开的place, 请选择运行测试的place:", options);
+        match prompt.raw_prompt() {
+            Ok(answer) => Ok(answer.value),
+            Err(InquireError::OperationCanceled | InquireError::OperationInterrupted) => {
+                Err(ExitCode::Interrupted)
+            }
+            Err(e) => {
+                eprintln!("{}", style(format!("选择place失败: {}", e)).red());
+                Err(ExitCode::ConfigError)
+            }
+        }
+    })
+    .await
+    .unwrap()
+}
@@ -0,0 +1,64 @@
+use crate::{
+    config::Config,
+    exit_code::ExitCode,
+    jest_results::JestResults,
+    reporters::{report_results, ReportOptions},
+    sourcemap_cache::SourcemapCache,
+};
+use console::style;
+
+/// `--use-rsp-json` 时读取的默认结果文件
+pub const RSP_JSON_FILE: &str = "rsp.json";
+
+/// 离线回放: 从文件读取已保存的 Jest 测试结果并打印, 不启动HTTP服务器
+///
+/// 返回与在线运行一致的退出码
+pub fn replay_results_file(
+    path: &str,
+    config: &Config,
+    options: &ReportOptions,
+    sourcemap_cache: &SourcemapCache,
+) -> ExitCode {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) => {
+            eprintln!(
+                "{}",
+                style(format!("无法读取测试结果文件 {}: {}", path, e))
+                    .red()
+                    .bold()
+            );
+            return ExitCode::ConfigError;
+        }
+    };
+
+    let jest_results: JestResults = match serde_json::from_str(&contents) {
+        Ok(jest_results) => jest_results,
+        Err(e) => {
+            eprintln!(
+                "{}",
+                style(format!("解析测试结果文件 {} 失败: {}", path, e))
+                    .red()
+                    .bold()
+            );
+            return ExitCode::MalformedResults;
+        }
+    };
+
+    if options.debug {
+        eprintln!(
+            "{}",
+            style(format!("成功从 {} 加载Jest测试结果", path)).green()
+        );
+    }
+
+    let sourcemap = sourcemap_cache.load(config, options.debug);
+    let reported = report_results(&jest_results, options, sourcemap.as_deref());
+
+    let pass_with_no_tests = config
+        .run_cli_options
+        .as_ref()
+        .and_then(|options| options.pass_with_no_tests)
+        .unwrap_or(false);
+    ExitCode::after_report(reported, &jest_results.results, pass_with_no_tests)
+}
@@ -0,0 +1,413 @@
+use super::{ReportContext, Reporter};
+use crate::{
+    code_frame::render_code_frame,
+    diff::diff_values,
+    jest_results::{Results, TestFile, TestResult},
+    stack_trace::{parse_stack, render_stack, StackLine},
+};
+use console::style;
+use serde_json::Value;
+use std::io::{self, Write};
+
+/// 默认的终端报告器: 文件结果输出到 stdout, 汇总和运行时错误输出到 stderr
+pub struct ConsoleReporter {
+    out: Box<dyn Write + Send>,
+    err: Box<dyn Write + Send>,
+    // 存在运行时错误的测试套件时, 只输出这些套件的错误
+    runtime_error_mode: bool,
+    prev_has_failure: bool,
+}
+
+impl ConsoleReporter {
+    pub fn new(out: Box<dyn Write + Send>, err: Box<dyn Write + Send>) -> Self {
+        ConsoleReporter {
+            out,
+            err,
+            runtime_error_mode: false,
+            prev_has_failure: false,
+        }
+    }
+
+    pub fn stdio() -> Self {
+        Self::new(Box::new(io::stdout()), Box::new(io::stderr()))
+    }
+
+    fn print_test_suites_summary(&mut self, results: &Results) -> io::Result<()> {
+        writeln!(
+            self.err,
+            "Test Suites: {} failed, {} passed, {} total",
+            style(results.num_failed_test_suites).red(),
+            results.num_passed_test_suites,
+            results.num_total_test_suites
+        )
+    }
+}
+
+impl Reporter for ConsoleReporter {
+    fn name(&self) -> &str {
+        "console"
+    }
+
+    fn on_run_start(&mut self, results: &Results, _ctx: &ReportContext) -> io::Result<()> {
+        self.prev_has_failure = false;
+        self.runtime_error_mode = results.num_runtime_error_test_suites > 0;
+
+        // 检查是否有运行时错误测试套件
+        if self.runtime_error_mode {
+            writeln!(
+                self.err,
+                "{}",
+                style(format!(
+                    "检测到{}个运行时错误测试套件",
+                    results.num_runtime_error_test_suites
+                ))
+                .red()
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn on_test_file_result(&mut self, test_file: &TestFile, ctx: &ReportContext) -> io::Result<()> {
+        let file_path = &test_file.test_file_path;
+
+        // 输出有运行时错误的测试文件
+        if self.runtime_error_mode {
+            if let Some(failure_message) = &test_file.failure_message {
+                writeln!(
+                    self.err,
+                    "{} {} {}",
+                    style("FAIL").red(),
+                    style("🚫").red(),
+                    style(file_path).cyan()
+                )?;
+                writeln!(self.err, "{}", style(failure_message).red())?;
+                writeln!(self.err)?;
+            }
+            return Ok(());
+        }
+
+        // 如果测试文件被跳过且没有失败的测试，则不显示
+        if test_file.skipped && test_file.num_failing_tests == 0 {
+            return Ok(());
+        }
+
+        let has_failure = test_file.has_failed_tests();
+        if ctx.only_failures && !has_failure {
+            return Ok(());
+        }
+
+        // 如果当前文件是失败的，而前一个文件是成功的，添加两行空行
+        if has_failure && !self.prev_has_failure {
+            writeln!(self.out)?;
+            writeln!(self.out)?;
+        }
+        self.prev_has_failure = has_failure;
+
+        if !has_failure {
+            return writeln!(
+                self.out,
+                "   {}   🟢 SERVER  {}",
+                style("PASS").green(),
+                file_path
+            );
+        }
+
+        let failed_tests = test_file
+            .test_results
+            .iter()
+            .filter(|test_result| test_result.status == "failed");
+
+        for (index, test_result) in failed_tests.enumerate() {
+            // 如果不是第一个测试错误，重新打印文件路径
+            if index > 0 {
+                writeln!(self.out)?;
+            }
+            writeln!(
+                self.out,
+                "   {}   🟢 SERVER  {}",
+                style("FAIL").red(),
+                file_path
+            )?;
+
+            for line in format_failure(test_result, ctx) {
+                writeln!(self.out, "{}", line)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn on_run_complete(&mut self, results: &Results, _ctx: &ReportContext) -> io::Result<()> {
+        // 运行时错误被视为失败, 只输出测试套件摘要
+        if self.runtime_error_mode {
+            self.print_test_suites_summary(results)?;
+            return self.err.flush();
+        }
+
+        // 输出测试结果摘要
+        self.print_test_suites_summary(results)?;
+        writeln!(
+            self.err,
+            "Tests:       {} failed, {} passed, {} total",
+            style(results.num_failed_tests).red(),
+            results.num_passed_tests,
+            results.num_total_tests
+        )?;
+        writeln!(self.err, "Snapshots:   {} total", results.snapshot.total)?;
+
+        // 时间计算 - 使用测试文件的运行时间
+        let time_display = if let Some(first_file) = results
+            .test_results
+            .iter()
+            .find(|tf| tf.perf_stats.end > 0 && tf.perf_stats.start > 0)
+        {
+            let runtime = (first_file.perf_stats.end - first_file.perf_stats.start) as f64;
+            runtime / 1000.0
+        } else {
+            // 回退到使用第一个测试文件的运行时间
+            results
+                .test_results
+                .first()
+                .map(|tf| tf.perf_stats.runtime as f64 / 1000.0)
+                .unwrap_or(0.0)
+        };
+
+        writeln!(self.err, "Time:        {:.3} s", time_display)?;
+
+        self.out.flush()?;
+        self.err.flush()
+    }
+}
+
+/// 构建单个失败测试用例的输出行
+fn format_failure(test_result: &TestResult, ctx: &ReportContext) -> Vec<String> {
+    let test_name = if test_result.ancestor_titles.is_empty() {
+        test_result.title.clone()
+    } else {
+        format!(
+            "{} › {}",
+            test_result.ancestor_titles.join(" › "),
+            test_result.title
+        )
+    };
+
+    let mut failure_detail = Vec::new();
+    failure_detail.push(format!("    ● {}", test_name));
+    failure_detail.push("".to_string());
+
+    // 如果没有详细信息，则使用简单的失败消息, 在第一个堆栈帧之前插入代码帧
+    if test_result.failure_details.is_empty() {
+        for msg in &test_result.failure_messages {
+            let stack = parse_stack(msg);
+            let code_frame = code_frame(msg, ctx);
+            // 消息部分原样输出, 从第一个堆栈帧开始按堆栈转换
+            let first_frame = stack
+                .iter()
+                .position(|line| matches!(line, StackLine::Frame(_)))
+                .unwrap_or(stack.len());
+
+            for line in &stack[..first_frame] {
+                failure_detail.push(format!(
+                    "    {}",
+                    line.to_local(ctx.sourcemap, ctx.file_links)
+                ));
+            }
+            if first_frame < stack.len() {
+                push_code_frame(&mut failure_detail, code_frame.as_deref());
+            }
+            for line in render_stack(
+                &stack[first_frame..],
+                ctx.sourcemap,
+                ctx.frame_filter,
+                ctx.file_links,
+            ) {
+                failure_detail.push(format!("    {}", line));
+            }
+        }
+        return failure_detail;
+    }
+
+    // 尝试从failureDetails中获取更详细的错误信息
+    for detail in &test_result.failure_details {
+        // 提取匹配结果信息
+        let Some(matcher_result) = detail.get("matcherResult") else {
+            continue;
+        };
+        let (Some(expected), Some(actual)) =
+            (matcher_result.get("expected"), matcher_result.get("actual"))
+        else {
+            continue;
+        };
+
+        for line in format_matcher_result(matcher_result, expected, actual) {
+            failure_detail.push(format!("    {}", line));
+        }
+        // 在 Expected/Received 信息后添加空行
+        failure_detail.push("".to_string());
+
+        // 直接从 __stack 获取堆栈信息
+        if let Some(stack_str) = detail.get("__stack").and_then(|stack| stack.as_str()) {
+            let stack_str = stack_str.trim_end_matches('\n');
+            let stack = parse_stack(stack_str);
+            push_code_frame(&mut failure_detail, code_frame(stack_str, ctx).as_deref());
+
+            // 输出堆栈信息, 有sourcemap时转换为本地路径, 折叠框架帧
+            for stack_line in render_stack(&stack, ctx.sourcemap, ctx.frame_filter, ctx.file_links)
+            {
+                failure_detail.push(format!("    {}", stack_line));
+            }
+            // 在stack结尾添加两个空行
+            failure_detail.push("".to_string());
+            failure_detail.push("".to_string());
+        }
+    }
+
+    failure_detail
+}
+
+/// 断言失败的说明: 匹配器消息, 以及期望值与实际值(对象、数组和多行字符串输出逐行差异)
+fn format_matcher_result(matcher_result: &Value, expected: &Value, actual: &Value) -> Vec<String> {
+    let mut lines = Vec::new();
+    let diff = diff_values(expected, actual);
+    let message = matcher_result.get("message").and_then(Value::as_str);
+
+    match message {
+        Some(message) => {
+            for line in message.lines() {
+                // 有差异时, 消息中原有的 Expected/Received 部分由差异代替
+                if diff.is_some() && is_expected_or_received(line) {
+                    break;
+                }
+                // 为Expected和Received添加颜色
+                lines.push(match line.split_once(':') {
+                    Some((label, value)) if label.trim() == "Expected" => {
+                        format!("{}: {}", label, style(value.trim()).green())
+                    }
+                    Some((label, value)) if label.trim() == "Received" => {
+                        format!("{}: {}", label, style(value.trim()).red())
+                    }
+                    _ => line.to_string(),
+                });
+            }
+            while lines.last().is_some_and(|line| line.trim().is_empty()) {
+                lines.pop();
+            }
+        }
+        None => {
+            // 没有message字段时, 按匹配器名称构建
+            let matcher = matcher_result.get("name").and_then(Value::as_str);
+            lines.push(match matcher {
+                Some(name) => format!(
+                    "expect({}).{}({}){}",
+                    style("received").red(),
+                    name,
+                    style("expected").green(),
+                    if name == "toBe" {
+                        " -- Object.is equality"
+                    } else {
+                        ""
+                    }
+                ),
+                None => format!("expect({})", style("received").red()),
+            });
+        }
+    }
+
+    // 消息中已包含标量的 Expected/Received, 只需补充差异或构建的说明
+    if let Some(diff) = diff {
+        lines.push("".to_string());
+        lines.extend(diff);
+    } else if message.is_none() {
+        lines.push("".to_string());
+        lines.push(format!("Expected: {}", style(expected).green()));
+        lines.push(format!("Received: {}", style(actual).red()));
+    }
+    lines
+}
+
+/// 消息中 Expected/Received 部分的起始行
+fn is_expected_or_received(line: &str) -> bool {
+    let line = line.trim_start();
+    ["Expected", "Received", "- Expected", "+ Received"]
+        .iter()
+        .any(|prefix| line.starts_with(prefix))
+}
+
+/// 失败位置的本地代码帧, 失败位置与其他报告器相同: 第一个能找到本地文件的用户代码帧
+fn code_frame(stack: &str, ctx: &ReportContext) -> Option<Vec<String>> {
+    let (file_path, line) = *ctx.user_frame_locations(stack).first()?;
+    render_code_frame(&ctx.file_links.absolute(file_path), line)
+}
+
+fn push_code_frame(failure_detail: &mut Vec<String>, code_frame: Option<&[String]>) {
+    if let Some(code_frame) = code_frame {
+        // 代码帧前后各保留一个空行
+        if failure_detail
+            .last()
+            .is_some_and(|line| !line.trim().is_empty())
+        {
+            failure_detail.push("".to_string());
+        }
+        failure_detail.extend(code_frame.iter().map(|line| format!("    {}", line)));
+        failure_detail.push("".to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::Config, file_links::FileLinks, rojo_sourcemap::RojoSourceMap,
+        stack_trace::FrameFilter,
+    };
+    use serde_json::json;
+
+    #[test]
+    fn code_frame_uses_first_resolvable_user_frame() {
+        let source = std::env::temp_dir().join(format!(
+            "jest-lua-companion-code-frame-{}.lua",
+            std::process::id()
+        ));
+        std::fs::write(&source, "local x = 1\nerror(\"boom\")\nreturn x\n").unwrap();
+
+        let sourcemap = RojoSourceMap::new(
+            &json!({
+                "name": "game",
+                "className": "DataModel",
+                "children": [{
+                    "name": "ServerScriptService",
+                    "className": "ServerScriptService",
+                    "children": [{
+                        "name": "math.spec",
+                        "className": "ModuleScript",
+                        "filePaths": [source.to_string_lossy()],
+                    }],
+                }],
+            })
+            .to_string(),
+        )
+        .unwrap();
+        let config: Config = serde_json::from_value(json!({ "roots": [] })).unwrap();
+        let file_links = FileLinks::new(&config, false);
+        let frame_filter = FrameFilter::default();
+        let ctx = ReportContext {
+            sourcemap: Some(&sourcemap),
+            frame_filter: &frame_filter,
+            file_links: &file_links,
+            only_failures: false,
+            debug: false,
+        };
+
+        // 第一个用户代码帧不在 Sourcemap 中, 使用之后能找到本地文件的帧
+        let frame = code_frame(
+            "boom\nServerScriptService.Missing:9\nServerScriptService.math.spec:2",
+            &ctx,
+        );
+        std::fs::remove_file(&source).unwrap();
+
+        let frame = frame.expect("应生成代码帧");
+        assert!(frame.iter().any(|line| line.contains("error(\"boom\")")));
+        assert!(frame.iter().any(|line| line.contains('^')));
+    }
+}
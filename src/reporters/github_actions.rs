@@ -0,0 +1,435 @@
+use super::{ReportContext, Reporter};
+use crate::{
+    file_links::FileLinks,
+    jest_results::{Results, TestFile, TestResult},
+    stack_trace::convert_stack_trace_text,
+};
+use std::{
+    fmt::Write as _,
+    fs::OpenOptions,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+/// GitHub Actions 报告器
+///
+/// 每个失败的测试输出一条 `::error` 工作流命令, 在 PR 的代码差异中直接标注失败位置;
+/// 运行结束后向 `$GITHUB_STEP_SUMMARY` 追加 Markdown 汇总表
+pub struct GithubActionsReporter {
+    out: Box<dyn Write + Send>,
+    /// 汇总文件, 未指定时使用 `$GITHUB_STEP_SUMMARY`
+    summary_file: Option<String>,
+    /// 仓库根目录, 标注中的文件路径相对于它; 未指定时使用 `$GITHUB_WORKSPACE`, 仍未设置时相对于项目根目录
+    workspace: Option<PathBuf>,
+    summary_rows: Vec<String>,
+    failures: Vec<String>,
+}
+
+impl GithubActionsReporter {
+    pub fn new(
+        out: Box<dyn Write + Send>,
+        summary_file: Option<String>,
+        workspace: Option<PathBuf>,
+    ) -> Self {
+        GithubActionsReporter {
+            out,
+            summary_file,
+            workspace,
+            summary_rows: Vec::new(),
+            failures: Vec::new(),
+        }
+    }
+
+    pub fn stdout(summary_file: Option<String>) -> Self {
+        let summary_file = summary_file.or_else(|| std::env::var("GITHUB_STEP_SUMMARY").ok());
+        let workspace = std::env::var_os("GITHUB_WORKSPACE").map(PathBuf::from);
+        Self::new(Box::new(io::stdout()), summary_file, workspace)
+    }
+
+    /// 输出一条 `::error` 命令, 并记录到汇总的失败列表
+    fn error(
+        &mut self,
+        title: &str,
+        message: &str,
+        location: Option<(String, u32)>,
+    ) -> io::Result<()> {
+        let mut properties = Vec::new();
+        if let Some((file, line)) = &location {
+            properties.push(format!("file={}", escape_property(file)));
+            properties.push(format!("line={}", line));
+        }
+        properties.push(format!("title={}", escape_property(title)));
+
+        writeln!(
+            self.out,
+            "::error {}::{}",
+            properties.join(","),
+            escape_data(message)
+        )?;
+
+        let location = location
+            .map(|(file, line)| format!("`{}:{}`", file, line))
+            .unwrap_or_default();
+        self.failures
+            .push(format!("| {} | {} |", escape_markdown(title), location));
+        Ok(())
+    }
+}
+
+impl Reporter for GithubActionsReporter {
+    fn name(&self) -> &str {
+        "github-actions"
+    }
+
+    fn on_run_start(&mut self, _results: &Results, _ctx: &ReportContext) -> io::Result<()> {
+        self.summary_rows.clear();
+        self.failures.clear();
+        Ok(())
+    }
+
+    fn on_test_file_result(&mut self, test_file: &TestFile, ctx: &ReportContext) -> io::Result<()> {
+        let links = ctx.file_links.plain();
+        let workspace = self.workspace.clone();
+        let workspace = workspace.as_deref();
+        let convert =
+            |text: &str| convert_stack_trace_text(text, ctx.sourcemap, ctx.frame_filter, &links);
+
+        if let Some(failure_message) = test_file.suite_failure() {
+            let title = format!("{}: Test suite failed to run", test_file.test_file_path);
+            let location = failure_location(failure_message, ctx, &links, workspace);
+            self.error(&title, &convert(failure_message), location)?;
+        }
+
+        for test_result in &test_file.test_results {
+            if test_result.status != "failed" {
+                continue;
+            }
+            let message = test_result
+                .failure_messages
+                .iter()
+                .map(|message| convert(message))
+                .collect::<Vec<_>>()
+                .join("\n\n");
+            let location = test_result_location(test_result, ctx, &links, workspace);
+            self.error(&test_result.full_name, &message, location)?;
+        }
+
+        let skipped = test_file.num_pending_tests + test_file.num_todo_tests;
+        let status = if test_file.failure_text().is_some() || test_file.has_failed_tests() {
+            "❌"
+        } else {
+            "✅"
+        };
+        self.summary_rows.push(format!(
+            "| {} `{}` | {} | {} | {} |",
+            status,
+            escape_markdown(&test_file.test_file_path),
+            test_file.num_passing_tests,
+            test_file.num_failing_tests,
+            skipped
+        ));
+        Ok(())
+    }
+
+    fn on_run_complete(&mut self, results: &Results, _ctx: &ReportContext) -> io::Result<()> {
+        self.out.flush()?;
+
+        let Some(summary_file) = &self.summary_file else {
+            return Ok(());
+        };
+
+        let mut summary = String::new();
+        let _ = writeln!(summary, "### Jest 测试结果\n");
+        let _ = writeln!(
+            summary,
+            "{} 个测试: {} 通过, {} 失败, {} 跳过\n",
+            results.num_total_tests,
+            results.num_passed_tests,
+            results.num_failed_tests,
+            results.num_pending_tests + results.num_todo_tests
+        );
+        summary.push_str("| 测试文件 | 通过 | 失败 | 跳过 |\n");
+        summary.push_str("| --- | ---: | ---: | ---: |\n");
+        for row in &self.summary_rows {
+            let _ = writeln!(summary, "{}", row);
+        }
+
+        if !self.failures.is_empty() {
+            summary.push_str("\n#### 失败的测试\n\n");
+            summary.push_str("| 测试 | 位置 |\n");
+            summary.push_str("| --- | --- |\n");
+            for row in &self.failures {
+                let _ = writeln!(summary, "{}", row);
+            }
+        }
+        summary.push('\n');
+
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(summary_file)?
+            .write_all(summary.as_bytes())
+    }
+}
+
+/// 失败测试的位置: 优先使用 `failureDetails` 中的 `__stack`, 其次是失败消息中的堆栈
+fn test_result_location(
+    test_result: &TestResult,
+    ctx: &ReportContext,
+    links: &FileLinks,
+    workspace: Option<&Path>,
+) -> Option<(String, u32)> {
+    let stacks = test_result
+        .failure_details
+        .iter()
+        .filter_map(|detail| detail.get("__stack").and_then(|stack| stack.as_str()));
+    stacks
+        .chain(test_result.failure_messages.iter().map(String::as_str))
+        .find_map(|stack| failure_location(stack, ctx, links, workspace))
+}
+
+/// 堆栈中第一个能在 Sourcemap 中找到本地文件的用户代码帧
+///
+/// GitHub 按仓库根目录解析 `file=`, 在子目录中运行时路径要相对于 `workspace`
+fn failure_location(
+    stack: &str,
+    ctx: &ReportContext,
+    links: &FileLinks,
+    workspace: Option<&Path>,
+) -> Option<(String, u32)> {
+    let (file_path, line) = *ctx.user_frame_locations(stack).first()?;
+    let file = match workspace {
+        Some(workspace) => links.display_relative_to(file_path, workspace),
+        None => links.display(file_path),
+    };
+    Some((file, line))
+}
+
+/// 转义工作流命令的消息部分
+fn escape_data(text: &str) -> String {
+    text.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// 转义工作流命令的属性值
+fn escape_property(text: &str) -> String {
+    escape_data(text).replace(':', "%3A").replace(',', "%2C")
+}
+
+fn escape_markdown(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::Config, rojo_sourcemap::RojoSourceMap, stack_trace::FrameFilter};
+    use serde_json::{json, Value};
+    use std::sync::{Arc, Mutex};
+
+    /// 测试中代替 stdout 的输出
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn test_file(path: &str, counts: [u32; 3], test_results: Value) -> Value {
+        json!({
+            "leaks": false,
+            "perfStats": { "runtime": 0, "slow": false, "start": 0, "end": 0 },
+            "skipped": false,
+            "snapshot": {},
+            "testFilePath": path,
+            "numPassingTests": counts[0],
+            "numFailingTests": counts[1],
+            "numPendingTests": counts[2],
+            "numTodoTests": 0,
+            "testResults": test_results,
+        })
+    }
+
+    fn test_result(full_name: &str, status: &str, failure_messages: &[&str]) -> Value {
+        json!({
+            "failureMessages": failure_messages,
+            "failureDetails": [],
+            "numPassingAsserts": 0,
+            "ancestorTitles": [],
+            "invocations": 1,
+            "status": status,
+            "fullName": full_name,
+            "retryReasons": [],
+            "title": full_name,
+        })
+    }
+
+    fn math_sourcemap() -> RojoSourceMap {
+        RojoSourceMap::new(
+            &json!({
+                "name": "game",
+                "className": "DataModel",
+                "children": [{
+                    "name": "ServerScriptService",
+                    "className": "ServerScriptService",
+                    "children": [{
+                        "name": "math.spec",
+                        "className": "ModuleScript",
+                        "filePaths": ["src/math.spec.lua"],
+                    }],
+                }],
+            })
+            .to_string(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn writes_error_commands_and_step_summary() {
+        let sourcemap = math_sourcemap();
+        let config: Config = serde_json::from_value(json!({ "roots": [] })).unwrap();
+        let file_links = FileLinks::new(&config, false);
+        let frame_filter = FrameFilter::default();
+        let ctx = ReportContext {
+            sourcemap: Some(&sourcemap),
+            frame_filter: &frame_filter,
+            file_links: &file_links,
+            only_failures: false,
+            debug: false,
+        };
+
+        let mut broken = test_file("ServerScriptService/broken.spec", [0, 0, 0], json!([]));
+        broken["failureMessage"] = json!("Requested module was required recursively");
+        let test_files = json!([
+            test_file(
+                "ServerScriptService/ok.spec",
+                [1, 0, 1],
+                json!([
+                    test_result("ok passes", "passed", &[]),
+                    test_result("ok skips", "pending", &[]),
+                ]),
+            ),
+            test_file(
+                "ServerScriptService/math.spec",
+                [0, 1, 0],
+                json!([test_result(
+                    "math: adds 1, 2",
+                    "failed",
+                    &["Expected: 100%\nReceived: 4\nServerScriptService.math.spec:7"],
+                )]),
+            ),
+            broken,
+        ]);
+
+        let results: Results = serde_json::from_value(json!({
+            "numPassedTests": 1,
+            "numPendingTestSuites": 0,
+            "numFailedTests": 1,
+            "numFailedTestSuites": 2,
+            "numPassedTestSuites": 1,
+            "snapshot": {},
+            "testResults": test_files,
+            "startTime": 0,
+            "openHandles": [],
+            "numTotalTests": 3,
+            "success": false,
+            "numTotalTestSuites": 3,
+            "numRuntimeErrorTestSuites": 1,
+            "numPendingTests": 1,
+            "numTodoTests": 0,
+            "wasInterrupted": false,
+        }))
+        .unwrap();
+
+        let summary_file = std::env::temp_dir().join(format!(
+            "jest-lua-companion-summary-{}.md",
+            std::process::id()
+        ));
+        std::fs::write(&summary_file, "已有内容\n").unwrap();
+
+        let out = SharedBuffer::default();
+        let mut reporter = GithubActionsReporter::new(
+            Box::new(out.clone()),
+            Some(summary_file.to_string_lossy().into_owned()),
+            None,
+        );
+        reporter.on_run_start(&results, &ctx).unwrap();
+        for test_file in &results.test_results {
+            reporter.on_test_file_result(test_file, &ctx).unwrap();
+        }
+        reporter.on_run_complete(&results, &ctx).unwrap();
+
+        let output = String::from_utf8(out.0.lock().unwrap().clone()).unwrap();
+        assert_eq!(
+            output.lines().collect::<Vec<_>>(),
+            [
+                "::error file=src/math.spec.lua,line=7,title=math%3A adds 1%2C 2::Expected: 100%25%0AReceived: 4%0Asrc/math.spec.lua:7",
+                "::error title=ServerScriptService/broken.spec%3A Test suite failed to run::Requested module was required recursively",
+            ]
+        );
+
+        let summary = std::fs::read_to_string(&summary_file).unwrap();
+        std::fs::remove_file(&summary_file).unwrap();
+        assert_eq!(
+            summary,
+            "已有内容\n\
+             ### Jest 测试结果\n\
+             \n\
+             3 个测试: 1 通过, 1 失败, 1 跳过\n\
+             \n\
+             | 测试文件 | 通过 | 失败 | 跳过 |\n\
+             | --- | ---: | ---: | ---: |\n\
+             | ✅ `ServerScriptService/ok.spec` | 1 | 0 | 1 |\n\
+             | ❌ `ServerScriptService/math.spec` | 0 | 1 | 0 |\n\
+             | ❌ `ServerScriptService/broken.spec` | 0 | 0 | 0 |\n\
+             \n\
+             #### 失败的测试\n\
+             \n\
+             | 测试 | 位置 |\n\
+             | --- | --- |\n\
+             | math: adds 1, 2 | `src/math.spec.lua:7` |\n\
+             | ServerScriptService/broken.spec: Test suite failed to run |  |\n\
+             \n"
+        );
+    }
+
+    #[test]
+    fn annotation_paths_are_relative_to_workspace() {
+        let sourcemap = math_sourcemap();
+        let config: Config = serde_json::from_value(json!({ "roots": [] })).unwrap();
+        let file_links = FileLinks::new(&config, false);
+        let frame_filter = FrameFilter::default();
+        let ctx = ReportContext {
+            sourcemap: Some(&sourcemap),
+            frame_filter: &frame_filter,
+            file_links: &file_links,
+            only_failures: false,
+            debug: false,
+        };
+        let stack = "ServerScriptService.math.spec:7";
+
+        // 在仓库的子目录中运行时, 路径要带上子目录
+        let cwd = std::env::current_dir().unwrap().canonicalize().unwrap();
+        let workspace = cwd.parent().unwrap();
+        let subdir = cwd.file_name().unwrap().to_string_lossy();
+        assert_eq!(
+            failure_location(stack, &ctx, &file_links, Some(workspace)),
+            Some((format!("{}/src/math.spec.lua", subdir), 7))
+        );
+        assert_eq!(
+            failure_location(stack, &ctx, &file_links, Some(&cwd)),
+            Some(("src/math.spec.lua".to_string(), 7))
+        );
+        assert_eq!(
+            failure_location(stack, &ctx, &file_links, None),
+            Some(("src/math.spec.lua".to_string(), 7))
+        );
+    }
+}
@@ -0,0 +1,194 @@
+use super::{ReportContext, Reporter};
+use crate::{
+    file_links::FileLinks,
+    jest_results::{Results, SnapshotStats, TestFile, TestResult},
+    sourcemap_trie::Lookup,
+    stack_trace::localize_stack_text,
+};
+use console::style;
+use serde::Serialize;
+use serde_json::Value;
+use std::io;
+
+/// 与 `jest --json` 格式相同的 JSON 报告器
+///
+/// `testFilePath` 通过 Sourcemap 转换为本地绝对路径, 堆栈转换为本地路径, 供 Jest 生态的工具直接使用
+pub struct JsonReporter {
+    output_file: String,
+    test_results: Vec<JsonTestFile>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonResults<'a> {
+    num_failed_test_suites: u32,
+    num_failed_tests: u32,
+    num_passed_test_suites: u32,
+    num_passed_tests: u32,
+    num_pending_test_suites: u32,
+    num_pending_tests: u32,
+    num_runtime_error_test_suites: u32,
+    num_todo_tests: u32,
+    num_total_test_suites: u32,
+    num_total_tests: u32,
+    open_handles: &'a [Value],
+    snapshot: &'a SnapshotStats,
+    start_time: u64,
+    success: bool,
+    was_interrupted: bool,
+    test_results: &'a [JsonTestFile],
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonTestFile {
+    assertion_results: Vec<JsonAssertion>,
+    start_time: u64,
+    end_time: u64,
+    message: String,
+    name: String,
+    status: &'static str,
+    summary: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonAssertion {
+    ancestor_titles: Vec<String>,
+    duration: Option<u32>,
+    failure_details: Vec<Value>,
+    failure_messages: Vec<String>,
+    full_name: String,
+    invocations: u32,
+    location: Option<Value>,
+    num_passing_asserts: u32,
+    retry_reasons: Vec<String>,
+    status: String,
+    title: String,
+}
+
+impl JsonReporter {
+    pub fn new(output_file: String) -> Self {
+        JsonReporter {
+            output_file,
+            test_results: Vec::new(),
+        }
+    }
+}
+
+impl Reporter for JsonReporter {
+    fn name(&self) -> &str {
+        "json"
+    }
+
+    fn on_run_start(&mut self, _results: &Results, _ctx: &ReportContext) -> io::Result<()> {
+        self.test_results.clear();
+        Ok(())
+    }
+
+    fn on_test_file_result(&mut self, test_file: &TestFile, ctx: &ReportContext) -> io::Result<()> {
+        let links = ctx.file_links.plain();
+        // 保留完整堆栈, 只转换路径, 不折叠框架帧
+        let convert = |text: &str| localize_stack_text(text, ctx.sourcemap, &links);
+
+        let failure_message = test_file.failure_text();
+        let failed = failure_message.is_some() || test_file.has_failed_tests();
+        self.test_results.push(JsonTestFile {
+            assertion_results: test_file
+                .test_results
+                .iter()
+                .map(|test_result| to_assertion(test_result, &convert))
+                .collect(),
+            start_time: test_file.perf_stats.start,
+            end_time: test_file.perf_stats.end,
+            message: failure_message.map(convert).unwrap_or_default(),
+            name: local_test_file_path(test_file, ctx, &links),
+            status: if failed { "failed" } else { "passed" },
+            summary: String::new(),
+        });
+        Ok(())
+    }
+
+    fn on_run_complete(&mut self, results: &Results, ctx: &ReportContext) -> io::Result<()> {
+        let document = JsonResults {
+            num_failed_test_suites: results.num_failed_test_suites,
+            num_failed_tests: results.num_failed_tests,
+            num_passed_test_suites: results.num_passed_test_suites,
+            num_passed_tests: results.num_passed_tests,
+            num_pending_test_suites: results.num_pending_test_suites,
+            num_pending_tests: results.num_pending_tests,
+            num_runtime_error_test_suites: results.num_runtime_error_test_suites,
+            num_todo_tests: results.num_todo_tests,
+            num_total_test_suites: results.num_total_test_suites,
+            num_total_tests: results.num_total_tests,
+            open_handles: &results.open_handles,
+            snapshot: &results.snapshot,
+            start_time: results.start_time,
+            success: results.is_success(),
+            was_interrupted: results.was_interrupted,
+            test_results: &self.test_results,
+        };
+
+        std::fs::write(&self.output_file, serde_json::to_string_pretty(&document)?)?;
+
+        if ctx.debug {
+            eprintln!(
+                "{}",
+                style(format!("已写入JSON报告: {}", self.output_file)).dim()
+            );
+        }
+        Ok(())
+    }
+}
+
+fn to_assertion(test_result: &TestResult, convert: &impl Fn(&str) -> String) -> JsonAssertion {
+    JsonAssertion {
+        ancestor_titles: test_result.ancestor_titles.clone(),
+        duration: test_result.duration,
+        failure_details: test_result
+            .failure_details
+            .iter()
+            .map(|detail| convert_failure_detail(detail, convert))
+            .collect(),
+        failure_messages: test_result
+            .failure_messages
+            .iter()
+            .map(|message| convert(message))
+            .collect(),
+        full_name: test_result.full_name.clone(),
+        invocations: test_result.invocations,
+        location: None,
+        num_passing_asserts: test_result.num_passing_asserts,
+        retry_reasons: test_result.retry_reasons.clone(),
+        status: test_result.status.clone(),
+        title: test_result.title.clone(),
+    }
+}
+
+/// 转换 `failureDetails` 中的堆栈字段
+fn convert_failure_detail(detail: &Value, convert: &impl Fn(&str) -> String) -> Value {
+    let mut detail = detail.clone();
+    if let Some(object) = detail.as_object_mut() {
+        for key in ["__stack", "stack"] {
+            if let Some(Value::String(stack)) = object.get_mut(key) {
+                *stack = convert(stack);
+            }
+        }
+    }
+    detail
+}
+
+/// 测试文件的本地绝对路径; 在 Sourcemap 中找不到时保留 DataModel 路径
+fn local_test_file_path(test_file: &TestFile, ctx: &ReportContext, links: &FileLinks) -> String {
+    let datamodel_path = test_file.test_file_path.replace('/', ".");
+    match ctx
+        .sourcemap
+        .map(|sourcemap| sourcemap.lookup(&datamodel_path))
+    {
+        Some(Lookup::Found(found)) => links
+            .absolute(found.file_path)
+            .to_string_lossy()
+            .into_owned(),
+        _ => test_file.test_file_path.clone(),
+    }
+}
@@ -0,0 +1,197 @@
+use super::{ReportContext, Reporter};
+use crate::{
+    file_links::FileLinks,
+    jest_results::{Results, TestFile, TestResult},
+    stack_trace::convert_stack_trace_text,
+};
+use console::style;
+use std::{fmt::Write as _, io};
+
+/// JUnit XML 报告器
+///
+/// 每个测试文件对应一个 `<testsuite>`, 每个测试用例对应一个 `<testcase>`
+pub struct JunitReporter {
+    output_file: String,
+    suites: String,
+}
+
+impl JunitReporter {
+    pub fn new(output_file: String) -> Self {
+        JunitReporter {
+            output_file,
+            suites: String::new(),
+        }
+    }
+}
+
+impl Reporter for JunitReporter {
+    fn name(&self) -> &str {
+        "junit"
+    }
+
+    fn on_run_start(&mut self, _results: &Results, _ctx: &ReportContext) -> io::Result<()> {
+        self.suites.clear();
+        Ok(())
+    }
+
+    fn on_test_file_result(&mut self, test_file: &TestFile, ctx: &ReportContext) -> io::Result<()> {
+        render_test_suite(&mut self.suites, test_file, ctx);
+        Ok(())
+    }
+
+    fn on_run_complete(&mut self, results: &Results, ctx: &ReportContext) -> io::Result<()> {
+        let total_time: f64 = results.test_results.iter().map(suite_time).sum();
+
+        let mut xml = String::new();
+        xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        let _ = writeln!(
+            xml,
+            "<testsuites name=\"jest tests\" tests=\"{}\" failures=\"{}\" errors=\"{}\" time=\"{:.3}\">",
+            results.num_total_tests,
+            results.num_failed_tests,
+            results.num_runtime_error_test_suites,
+            total_time
+        );
+        xml.push_str(&self.suites);
+        xml.push_str("</testsuites>\n");
+
+        std::fs::write(&self.output_file, xml)?;
+
+        if ctx.debug {
+            eprintln!(
+                "{}",
+                style(format!("已写入JUnit报告: {}", self.output_file)).dim()
+            );
+        }
+        Ok(())
+    }
+}
+
+fn render_test_suite(xml: &mut String, test_file: &TestFile, ctx: &ReportContext) {
+    // 报告文件中不使用终端超链接
+    let links = ctx.file_links.plain();
+    let skipped = test_file.num_pending_tests + test_file.num_todo_tests;
+    let errors = u32::from(test_file.suite_failure().is_some());
+    let tests = if test_file.test_results.is_empty() {
+        errors
+    } else {
+        test_file.test_results.len() as u32
+    };
+
+    let _ = writeln!(
+        xml,
+        "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{:.3}\">",
+        escape_xml(&test_file.test_file_path),
+        tests,
+        test_file.num_failing_tests,
+        errors,
+        skipped,
+        suite_time(test_file)
+    );
+
+    if let Some(failure_message) = test_file.suite_failure() {
+        let _ = writeln!(
+            xml,
+            "    <testcase classname=\"{}\" name=\"Test suite failed to run\" time=\"0.000\">",
+            escape_xml(&test_file.test_file_path)
+        );
+        let _ = writeln!(
+            xml,
+            "      <error message=\"Test suite failed to run\">{}</error>",
+            escape_xml(&convert_stack_trace_text(
+                failure_message,
+                ctx.sourcemap,
+                ctx.frame_filter,
+                &links
+            ))
+        );
+        xml.push_str("    </testcase>\n");
+    }
+
+    for test_result in &test_file.test_results {
+        render_test_case(xml, test_file, test_result, ctx, &links);
+    }
+
+    xml.push_str("  </testsuite>\n");
+}
+
+fn render_test_case(
+    xml: &mut String,
+    test_file: &TestFile,
+    test_result: &TestResult,
+    ctx: &ReportContext,
+    links: &FileLinks,
+) {
+    let classname = if test_result.ancestor_titles.is_empty() {
+        test_file.test_file_path.clone()
+    } else {
+        test_result.ancestor_titles.join(" ")
+    };
+    let time = test_result.duration.unwrap_or(0) as f64 / 1000.0;
+
+    let _ = write!(
+        xml,
+        "    <testcase classname=\"{}\" name=\"{}\" time=\"{:.3}\"",
+        escape_xml(&classname),
+        escape_xml(&test_result.full_name),
+        time
+    );
+
+    match test_result.status.as_str() {
+        "failed" => {
+            xml.push_str(">\n");
+            let message = test_result
+                .failure_messages
+                .first()
+                .and_then(|msg| msg.lines().next())
+                .unwrap_or("");
+            let body = test_result
+                .failure_messages
+                .iter()
+                .map(|msg| convert_stack_trace_text(msg, ctx.sourcemap, ctx.frame_filter, links))
+                .collect::<Vec<_>>()
+                .join("\n\n");
+            let _ = writeln!(
+                xml,
+                "      <failure message=\"{}\">{}</failure>",
+                escape_xml(message),
+                escape_xml(&body)
+            );
+            xml.push_str("    </testcase>\n");
+        }
+        "pending" | "skipped" | "todo" | "disabled" => {
+            xml.push_str(">\n      <skipped/>\n    </testcase>\n");
+        }
+        _ => {
+            xml.push_str("/>\n");
+        }
+    }
+}
+
+/// 测试文件的运行时间(秒)
+fn suite_time(test_file: &TestFile) -> f64 {
+    let perf_stats = &test_file.perf_stats;
+    if perf_stats.end > perf_stats.start && perf_stats.start > 0 {
+        (perf_stats.end - perf_stats.start) as f64 / 1000.0
+    } else {
+        perf_stats.runtime as f64 / 1000.0
+    }
+}
+
+/// 转义 XML 特殊字符, 并去除 XML 1.0 不允许的控制字符
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if (c as u32) < 0x20 => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,307 @@
+use crate::{
+    file_links::FileLinks,
+    jest_results::{JestResults, Results, TestFile},
+    rojo_sourcemap::RojoSourceMap,
+    sourcemap_trie::Lookup,
+    stack_trace::{parse_stack, FrameFilter, StackLine},
+};
+use ::console::style;
+use std::{
+    io,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+pub mod console;
+pub mod github_actions;
+pub mod json;
+pub mod junit;
+pub mod sarif;
+pub mod tap;
+
+pub use self::console::ConsoleReporter;
+pub use github_actions::GithubActionsReporter;
+pub use json::JsonReporter;
+pub use junit::JunitReporter;
+pub use sarif::SarifReporter;
+pub use tap::TapReporter;
+
+/// 报告器在各个阶段可使用的上下文
+pub struct ReportContext<'a> {
+    pub sourcemap: Option<&'a RojoSourceMap>,
+    pub frame_filter: &'a FrameFilter,
+    pub file_links: &'a FileLinks,
+    pub only_failures: bool,
+    pub debug: bool,
+}
+
+impl<'a> ReportContext<'a> {
+    /// 堆栈中能在 Sourcemap 中找到本地文件的用户代码帧, 依次返回 (Sourcemap 中的文件路径, 行号)
+    pub fn user_frame_locations(&self, stack: &str) -> Vec<(&'a str, u32)> {
+        let Some(sourcemap) = self.sourcemap else {
+            return Vec::new();
+        };
+
+        parse_stack(stack)
+            .iter()
+            .filter_map(|line| match line {
+                StackLine::Frame(frame) if !self.frame_filter.is_framework(frame) => {
+                    match frame.resolve(sourcemap) {
+                        Lookup::Found(found) => Some((found.file_path, frame.line)),
+                        _ => None,
+                    }
+                }
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// 测试结果报告器
+///
+/// 一次运行中的调用顺序为 `on_run_start` → 每个测试文件一次 `on_test_file_result` → `on_run_complete`
+pub trait Reporter {
+    /// 报告器名称, 用于错误提示
+    fn name(&self) -> &str;
+
+    /// 收到测试结果, 开始报告
+    fn on_run_start(&mut self, _results: &Results, _ctx: &ReportContext) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// 单个测试文件的结果
+    fn on_test_file_result(
+        &mut self,
+        _test_file: &TestFile,
+        _ctx: &ReportContext,
+    ) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// 所有测试文件报告完毕, 文件类报告器在此写出并刷新
+    fn on_run_complete(&mut self, _results: &Results, _ctx: &ReportContext) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// 内置报告器类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReporterKind {
+    /// 终端输出
+    Console,
+    /// JUnit XML 文件
+    Junit,
+    /// 与 `jest --json` 相同格式的 JSON 文件
+    Json,
+    /// GitHub Actions 工作流命令和步骤汇总
+    GithubActions,
+    /// SARIF 2.1.0 文件
+    Sarif,
+    /// TAP version 14, 未指定路径时输出到 stdout
+    Tap,
+}
+
+impl ReporterKind {
+    fn name(self) -> &'static str {
+        match self {
+            ReporterKind::Console => "console",
+            ReporterKind::Junit => "junit",
+            ReporterKind::Json => "json",
+            ReporterKind::GithubActions => "github-actions",
+            ReporterKind::Sarif => "sarif",
+            ReporterKind::Tap => "tap",
+        }
+    }
+
+    /// 文件类报告器未指定输出路径时使用的默认文件
+    fn default_output_file(self) -> Option<&'static str> {
+        match self {
+            ReporterKind::Console => None,
+            ReporterKind::Junit => Some("junit.xml"),
+            ReporterKind::Json => Some("jest-results.json"),
+            ReporterKind::Sarif => Some("jest-results.sarif"),
+            ReporterKind::Tap => None,
+            // 汇总默认写入 $GITHUB_STEP_SUMMARY
+            ReporterKind::GithubActions => None,
+        }
+    }
+}
+
+/// 报告器配置, 格式为 `name[=path]`, 例如 `console`, `junit=report.xml`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReporterSpec {
+    pub kind: ReporterKind,
+    pub output_file: Option<String>,
+}
+
+impl FromStr for ReporterSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, output_file) = match s.split_once('=') {
+            Some((name, path)) => (name.trim(), Some(path.trim().to_string())),
+            None => (s.trim(), None),
+        };
+
+        let kind = match name {
+            "console" | "default" => ReporterKind::Console,
+            "junit" => ReporterKind::Junit,
+            "json" => ReporterKind::Json,
+            "github-actions" | "github" => ReporterKind::GithubActions,
+            "sarif" => ReporterKind::Sarif,
+            "tap" => ReporterKind::Tap,
+            _ => {
+                return Err(format!(
+                    "未知的报告器 \"{}\", 可选值: console, junit, json, github-actions, sarif, tap",
+                    name
+                ))
+            }
+        };
+
+        if kind == ReporterKind::Console && output_file.is_some() {
+            return Err("console 报告器不支持输出文件".to_string());
+        }
+
+        Ok(ReporterSpec { kind, output_file })
+    }
+}
+
+/// 报告测试结果时使用的选项
+#[derive(Debug, Clone)]
+pub struct ReportOptions {
+    pub reporters: Vec<ReporterSpec>,
+    /// 文件类报告器未在 `name=path` 中指定路径时, 默认文件写入此目录
+    pub reports_dir: Option<PathBuf>,
+    pub frame_filter: FrameFilter,
+    pub file_links: FileLinks,
+    pub only_failures: bool,
+    pub debug: bool,
+}
+
+impl ReportOptions {
+    /// 报告器实际写入的文件; 输出到终端的报告器为 `None`
+    fn resolved_output_file(&self, spec: &ReporterSpec) -> Option<String> {
+        match spec.kind {
+            ReporterKind::Console => None,
+            // 汇总文件只能通过 `name=path` 指定, 否则使用 $GITHUB_STEP_SUMMARY
+            ReporterKind::GithubActions => spec.output_file.clone(),
+            _ => spec.output_file.clone().or_else(|| {
+                let file_name = spec.kind.default_output_file()?;
+                Some(match &self.reports_dir {
+                    Some(dir) => dir.join(file_name).to_string_lossy().into_owned(),
+                    None => file_name.to_string(),
+                })
+            }),
+        }
+    }
+
+    /// 检查是否有多个报告器写入同一个文件, 否则后写入的报告会覆盖前面的
+    pub fn check_output_files(&self) -> Result<(), String> {
+        let mut seen: Vec<(String, &ReporterSpec)> = Vec::new();
+        for spec in &self.reporters {
+            let Some(output_file) = self.resolved_output_file(spec) else {
+                continue;
+            };
+            if let Some((_, other)) = seen
+                .iter()
+                .find(|(path, _)| Path::new(path) == Path::new(&output_file))
+            {
+                return Err(format!(
+                    "报告器 {} 和 {} 的输出文件都是 \"{}\", 请用 name=path 分别指定",
+                    other.kind.name(),
+                    spec.kind.name(),
+                    output_file
+                ));
+            }
+            seen.push((output_file, spec));
+        }
+        Ok(())
+    }
+}
+
+/// 根据配置创建报告器
+pub fn create_reporters(options: &ReportOptions) -> Vec<Box<dyn Reporter + Send>> {
+    options
+        .reporters
+        .iter()
+        .map(|spec| -> Box<dyn Reporter + Send> {
+            let output_file = options.resolved_output_file(spec);
+
+            match spec.kind {
+                ReporterKind::Console => Box::new(ConsoleReporter::stdio()),
+                ReporterKind::Junit => {
+                    Box::new(JunitReporter::new(output_file.unwrap_or_default()))
+                }
+                ReporterKind::Json => Box::new(JsonReporter::new(output_file.unwrap_or_default())),
+                ReporterKind::Sarif => {
+                    Box::new(SarifReporter::new(output_file.unwrap_or_default()))
+                }
+                ReporterKind::Tap => Box::new(TapReporter::new(output_file)),
+                ReporterKind::GithubActions => Box::new(GithubActionsReporter::stdout(output_file)),
+            }
+        })
+        .collect()
+}
+
+/// 依次调用所有报告器输出测试结果
+///
+/// 某个报告器失败时仍会运行其余的报告器, 错误在输出后返回第一个
+pub fn report_results(
+    results: &JestResults,
+    options: &ReportOptions,
+    sourcemap: Option<&RojoSourceMap>,
+) -> io::Result<()> {
+    let ctx = ReportContext {
+        sourcemap,
+        frame_filter: &options.frame_filter,
+        file_links: &options.file_links,
+        only_failures: options.only_failures,
+        debug: options.debug,
+    };
+    let mut reporters = create_reporters(options);
+    let results = &results.results;
+
+    // 成功的文件在前, 失败的文件在后, 同类按路径排序
+    let mut test_files: Vec<&TestFile> = results.test_results.iter().collect();
+    test_files.sort_by(|a, b| {
+        a.has_failed_tests()
+            .cmp(&b.has_failed_tests())
+            .then_with(|| a.test_file_path.cmp(&b.test_file_path))
+    });
+
+    let mut first_error = None;
+    if let Some(reports_dir) = &options.reports_dir {
+        if let Err(e) = std::fs::create_dir_all(reports_dir) {
+            eprintln!(
+                "{}",
+                style(format!(
+                    "创建报告目录 {} 失败: {}",
+                    reports_dir.display(),
+                    e
+                ))
+                .red()
+            );
+            first_error = Some(e);
+        }
+    }
+    for reporter in reporters.iter_mut() {
+        let outcome = reporter
+            .on_run_start(results, &ctx)
+            .and_then(|_| {
+                test_files
+                    .iter()
+                    .try_for_each(|test_file| reporter.on_test_file_result(test_file, &ctx))
+            })
+            .and_then(|_| reporter.on_run_complete(results, &ctx));
+
+        if let Err(e) = outcome {
+            eprintln!(
+                "{}",
+                style(format!("报告器 {} 输出失败: {}", reporter.name(), e)).red()
+            );
+            first_error.get_or_insert(e);
+        }
+    }
+
+    first_error.map_or(Ok(()), Err)
+}
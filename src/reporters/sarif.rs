@@ -0,0 +1,225 @@
+use super::{ReportContext, Reporter};
+use crate::{
+    file_links::FileLinks,
+    jest_results::{Results, TestFile, TestResult},
+    stack_trace::convert_stack_trace_text,
+};
+use console::style;
+use serde_json::{json, Value};
+use std::{io, path::Path};
+
+/// 没有 `matcherResult` 的失败(如抛出的错误)使用的规则 ID
+const FAILURE_RULE_ID: &str = "test-failure";
+/// 项目根目录, 结果中的相对路径基于此
+const SRCROOT: &str = "%SRCROOT%";
+
+/// SARIF 2.1.0 报告器
+///
+/// 每个失败的断言对应一个 result, 规则 ID 为匹配器名称; 运行失败的测试套件作为工具执行通知
+pub struct SarifReporter {
+    output_file: String,
+    /// 按出现顺序记录的规则 ID, result 中的 `ruleIndex` 指向此列表
+    rules: Vec<String>,
+    results: Vec<Value>,
+    notifications: Vec<Value>,
+}
+
+impl SarifReporter {
+    pub fn new(output_file: String) -> Self {
+        SarifReporter {
+            output_file,
+            rules: Vec::new(),
+            results: Vec::new(),
+            notifications: Vec::new(),
+        }
+    }
+
+    fn rule_index(&mut self, rule_id: &str) -> usize {
+        match self.rules.iter().position(|rule| rule == rule_id) {
+            Some(index) => index,
+            None => {
+                self.rules.push(rule_id.to_string());
+                self.rules.len() - 1
+            }
+        }
+    }
+
+    /// 失败测试的每个断言对应一个 result; `failureMessages` 与 `failureDetails` 按顺序一一对应
+    fn add_test_result(
+        &mut self,
+        test_file: &TestFile,
+        test_result: &TestResult,
+        ctx: &ReportContext,
+        links: &FileLinks,
+    ) {
+        let count = test_result
+            .failure_messages
+            .len()
+            .max(test_result.failure_details.len());
+
+        for index in 0..count {
+            let message = test_result.failure_messages.get(index);
+            let detail = test_result.failure_details.get(index);
+            let rule_id = detail
+                .and_then(|detail| detail.get("matcherResult"))
+                .and_then(|matcher_result| matcher_result.get("name"))
+                .and_then(Value::as_str)
+                .unwrap_or(FAILURE_RULE_ID);
+            let rule_index = self.rule_index(rule_id);
+
+            // 位置优先来自 __stack, 其次是失败消息中的堆栈
+            let stack = detail
+                .and_then(|detail| detail.get("__stack"))
+                .and_then(Value::as_str)
+                .or(message.map(String::as_str))
+                .unwrap_or_default();
+            let frames: Vec<Value> = ctx
+                .user_frame_locations(stack)
+                .into_iter()
+                .map(|(file_path, line)| location(links, file_path, line))
+                .collect();
+
+            let text = message
+                .map(|message| {
+                    convert_stack_trace_text(message, ctx.sourcemap, ctx.frame_filter, links)
+                })
+                .unwrap_or_else(|| test_result.full_name.clone());
+
+            let mut result = json!({
+                "ruleId": rule_id,
+                "ruleIndex": rule_index,
+                "level": "error",
+                "message": { "text": text },
+                "locations": frames.first().cloned().into_iter().collect::<Vec<_>>(),
+                "properties": {
+                    "testFilePath": test_file.test_file_path,
+                    "fullName": test_result.full_name,
+                },
+            });
+            if !frames.is_empty() {
+                result["stacks"] = json!([{
+                    "frames": frames
+                        .into_iter()
+                        .map(|location| json!({ "location": location }))
+                        .collect::<Vec<_>>(),
+                }]);
+            }
+            self.results.push(result);
+        }
+    }
+}
+
+impl Reporter for SarifReporter {
+    fn name(&self) -> &str {
+        "sarif"
+    }
+
+    fn on_run_start(&mut self, _results: &Results, _ctx: &ReportContext) -> io::Result<()> {
+        self.rules.clear();
+        self.results.clear();
+        self.notifications.clear();
+        Ok(())
+    }
+
+    fn on_test_file_result(&mut self, test_file: &TestFile, ctx: &ReportContext) -> io::Result<()> {
+        let links = ctx.file_links.plain();
+
+        // 测试套件运行失败(例如模块加载错误)不属于断言失败, 作为工具执行通知
+        if let Some(failure_message) = &test_file.failure_message {
+            let locations: Vec<Value> = ctx
+                .user_frame_locations(failure_message)
+                .into_iter()
+                .take(1)
+                .map(|(file_path, line)| location(&links, file_path, line))
+                .collect();
+            self.notifications.push(json!({
+                "level": "error",
+                "message": {
+                    "text": format!(
+                        "{}: {}",
+                        test_file.test_file_path,
+                        convert_stack_trace_text(
+                            failure_message,
+                            ctx.sourcemap,
+                            ctx.frame_filter,
+                            &links
+                        )
+                    ),
+                },
+                "locations": locations,
+            }));
+        }
+
+        for test_result in &test_file.test_results {
+            if test_result.status == "failed" {
+                self.add_test_result(test_file, test_result, ctx, &links);
+            }
+        }
+        Ok(())
+    }
+
+    fn on_run_complete(&mut self, results: &Results, ctx: &ReportContext) -> io::Result<()> {
+        let rules: Vec<Value> = self
+            .rules
+            .iter()
+            .map(|rule_id| {
+                let description = if rule_id == FAILURE_RULE_ID {
+                    "测试失败".to_string()
+                } else {
+                    format!("expect(...).{} 断言失败", rule_id)
+                };
+                json!({ "id": rule_id, "shortDescription": { "text": description } })
+            })
+            .collect();
+
+        let sarif = json!({
+            "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+            "version": "2.1.0",
+            "runs": [{
+                "tool": {
+                    "driver": {
+                        "name": env!("CARGO_PKG_NAME"),
+                        "version": env!("CARGO_PKG_VERSION"),
+                        "informationUri": env!("CARGO_PKG_REPOSITORY"),
+                        "rules": rules,
+                    },
+                },
+                "originalUriBaseIds": {
+                    SRCROOT: { "uri": ctx.file_links.project_root_url() },
+                },
+                "invocations": [{
+                    "executionSuccessful": results.num_runtime_error_test_suites == 0,
+                    "toolExecutionNotifications": self.notifications,
+                }],
+                "results": self.results,
+            }],
+        });
+
+        std::fs::write(&self.output_file, serde_json::to_string_pretty(&sarif)?)?;
+
+        if ctx.debug {
+            eprintln!(
+                "{}",
+                style(format!("已写入SARIF报告: {}", self.output_file)).dim()
+            );
+        }
+        Ok(())
+    }
+}
+
+/// SARIF 中的物理位置; 项目内的文件使用相对于 `%SRCROOT%` 的路径
+fn location(links: &FileLinks, file_path: &str, line: u32) -> Value {
+    let display = links.display(file_path);
+    let artifact_location = if Path::new(&display).is_absolute() {
+        json!({ "uri": links.file_url(file_path) })
+    } else {
+        json!({ "uri": display.replace(' ', "%20"), "uriBaseId": SRCROOT })
+    };
+
+    json!({
+        "physicalLocation": {
+            "artifactLocation": artifact_location,
+            "region": { "startLine": line },
+        },
+    })
+}
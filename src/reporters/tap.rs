@@ -0,0 +1,233 @@
+use super::{ReportContext, Reporter};
+use crate::{
+    jest_results::{Results, TestFile, TestResult},
+    stack_trace::convert_stack_trace_text,
+};
+use serde_json::Value;
+use std::{
+    fmt::Write as _,
+    io::{self, Write},
+};
+
+/// 子测试的缩进
+const SUBTEST_INDENT: &str = "    ";
+
+/// TAP version 14 报告器
+///
+/// 每个测试文件是一个子测试, 其中每个测试用例是一个测试点; 失败的测试附带 YAML 诊断信息
+pub struct TapReporter {
+    /// 输出文件, 未指定时输出到 stdout
+    output_file: Option<String>,
+    out: Box<dyn Write + Send>,
+    test_file_count: usize,
+}
+
+impl TapReporter {
+    pub fn new(output_file: Option<String>) -> Self {
+        TapReporter {
+            output_file,
+            out: Box::new(io::stdout()),
+            test_file_count: 0,
+        }
+    }
+}
+
+impl Reporter for TapReporter {
+    fn name(&self) -> &str {
+        "tap"
+    }
+
+    fn on_run_start(&mut self, results: &Results, _ctx: &ReportContext) -> io::Result<()> {
+        self.test_file_count = 0;
+        if let Some(path) = &self.output_file {
+            self.out = Box::new(io::BufWriter::new(std::fs::File::create(path)?));
+        }
+        writeln!(self.out, "TAP version 14")?;
+        writeln!(self.out, "1..{}", results.test_results.len())
+    }
+
+    fn on_test_file_result(&mut self, test_file: &TestFile, ctx: &ReportContext) -> io::Result<()> {
+        self.test_file_count += 1;
+        let number = self.test_file_count;
+        let description = escape_description(&test_file.test_file_path);
+        let links = ctx.file_links.plain();
+        let convert =
+            |text: &str| convert_stack_trace_text(text, ctx.sourcemap, ctx.frame_filter, &links);
+
+        if let Some(failure_message) = test_file.suite_failure() {
+            let mut tap = String::new();
+            let _ = writeln!(tap, "not ok {} - {}", number, description);
+            let mut diagnostics = Vec::new();
+            push_yaml(&mut diagnostics, "message", "Test suite failed to run");
+            push_yaml(&mut diagnostics, "stack", &convert(failure_message));
+            write_diagnostics(&mut tap, "", &diagnostics);
+            return self.out.write_all(tap.as_bytes());
+        }
+
+        if test_file.test_results.is_empty() {
+            return writeln!(self.out, "ok {} - {} # SKIP", number, description);
+        }
+
+        let mut tap = String::new();
+        let _ = writeln!(tap, "# Subtest: {}", description);
+        let _ = writeln!(tap, "{}1..{}", SUBTEST_INDENT, test_file.test_results.len());
+        for (index, test_result) in test_file.test_results.iter().enumerate() {
+            render_test_point(&mut tap, index + 1, test_result, &convert);
+        }
+
+        let ok = test_file.failure_message.is_none() && !test_file.has_failed_tests();
+        let _ = writeln!(
+            tap,
+            "{} {} - {}",
+            if ok { "ok" } else { "not ok" },
+            number,
+            description
+        );
+        self.out.write_all(tap.as_bytes())
+    }
+
+    fn on_run_complete(&mut self, _results: &Results, _ctx: &ReportContext) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// 子测试中的一个测试点, 失败时附带 YAML 诊断信息
+fn render_test_point(
+    tap: &mut String,
+    number: usize,
+    test_result: &TestResult,
+    convert: &impl Fn(&str) -> String,
+) {
+    let description = escape_description(&test_result.full_name);
+    let line = match test_result.status.as_str() {
+        "passed" => format!("ok {} - {}", number, description),
+        "pending" | "skipped" | "disabled" => format!("ok {} - {} # SKIP", number, description),
+        "todo" => format!("not ok {} - {} # TODO", number, description),
+        _ => format!("not ok {} - {}", number, description),
+    };
+    let _ = writeln!(tap, "{}{}", SUBTEST_INDENT, line);
+
+    if test_result.status == "failed" {
+        write_diagnostics(
+            tap,
+            SUBTEST_INDENT,
+            &failure_diagnostics(test_result, convert),
+        );
+    }
+}
+
+/// 失败测试的诊断信息: 匹配器、期望值、实际值以及转换后的堆栈
+fn failure_diagnostics(test_result: &TestResult, convert: &impl Fn(&str) -> String) -> Vec<String> {
+    let mut diagnostics = Vec::new();
+    let detail = test_result
+        .failure_details
+        .iter()
+        .find(|detail| detail.get("matcherResult").is_some());
+    let matcher_result = detail.and_then(|detail| detail.get("matcherResult"));
+    let message = matcher_result
+        .and_then(|matcher_result| matcher_result.get("message"))
+        .and_then(Value::as_str);
+    let stack = detail
+        .and_then(|detail| detail.get("__stack"))
+        .and_then(Value::as_str);
+
+    match (message, stack) {
+        (Some(message), Some(stack)) => {
+            push_yaml(&mut diagnostics, "message", message);
+            push_yaml(&mut diagnostics, "severity", "fail");
+            push_matcher_result(&mut diagnostics, matcher_result);
+            push_yaml(&mut diagnostics, "stack", &convert(stack));
+        }
+        _ => {
+            // 没有结构化信息时, 失败消息中已包含堆栈
+            let message = test_result
+                .failure_messages
+                .iter()
+                .map(|message| convert(message))
+                .collect::<Vec<_>>()
+                .join("\n\n");
+            push_yaml(&mut diagnostics, "message", &message);
+            push_yaml(&mut diagnostics, "severity", "fail");
+            push_matcher_result(&mut diagnostics, matcher_result);
+        }
+    }
+    diagnostics
+}
+
+fn push_matcher_result(diagnostics: &mut Vec<String>, matcher_result: Option<&Value>) {
+    let Some(matcher_result) = matcher_result else {
+        return;
+    };
+    if let Some(name) = matcher_result.get("name").and_then(Value::as_str) {
+        push_yaml(diagnostics, "matcher", name);
+    }
+    // JSON 是合法的 YAML 流式写法
+    for key in ["expected", "actual"] {
+        if let Some(value) = matcher_result.get(key) {
+            diagnostics.push(format!("{}: {}", key, value));
+        }
+    }
+}
+
+/// 添加 YAML 字段, 多行文本使用块标量
+///
+/// 块标量显式指定缩进为 2, 否则第一行本身带缩进(如差异输出)时 YAML 会推断出更深的缩进, 导致后续行无法解析
+fn push_yaml(diagnostics: &mut Vec<String>, key: &str, text: &str) {
+    let text = text.trim_end();
+    if text.contains('\n') {
+        diagnostics.push(format!("{}: |2-", key));
+        diagnostics.extend(text.lines().map(|line| format!("  {}", line)));
+    } else {
+        diagnostics.push(format!("{}: {}", key, Value::from(text)));
+    }
+}
+
+/// YAML 诊断块, 缩进比测试点多两个空格
+fn write_diagnostics(tap: &mut String, indent: &str, diagnostics: &[String]) {
+    let _ = writeln!(tap, "{}  ---", indent);
+    for line in diagnostics {
+        let _ = writeln!(tap, "{}  {}", indent, line);
+    }
+    let _ = writeln!(tap, "{}  ...", indent);
+}
+
+/// 描述中的 `#` 和 `\` 需要转义, 否则会被当作指令
+fn escape_description(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('#', "\\#")
+        .replace('\n', " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn block_scalar_keeps_indented_first_line() {
+        let mut diagnostics = Vec::new();
+        push_yaml(
+            &mut diagnostics,
+            "message",
+            "  - Expected\n  + Received\n\nExpected: 2\n",
+        );
+        push_yaml(&mut diagnostics, "severity", "fail");
+        assert_eq!(
+            diagnostics,
+            [
+                "message: |2-",
+                "    - Expected",
+                "    + Received",
+                "  ",
+                "  Expected: 2",
+                "severity: \"fail\"",
+            ]
+        );
+
+        let mut tap = String::new();
+        write_diagnostics(&mut tap, SUBTEST_INDENT, &diagnostics[..3]);
+        assert_eq!(
+            tap,
+            "      ---\n      message: |2-\n          - Expected\n          + Received\n      ...\n"
+        );
+    }
+}
@@ -0,0 +1,601 @@
+use serde::Deserialize;
+use serde_json::Value;
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::rojo_sourcemap::RojoSourceMapNode;
+
+/// 默认的 Rojo 项目文件
+pub const DEFAULT_PROJECT_FILE: &str = "default.project.json";
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ProjectFile {
+    name: String,
+    tree: ProjectNode,
+    #[serde(default)]
+    glob_ignore_paths: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ProjectNode {
+    #[serde(rename = "$className")]
+    class_name: Option<String>,
+    #[serde(rename = "$path")]
+    path: Option<ProjectPath>,
+    /// 子节点; 以 `$` 开头的其他键(`$properties` 等)与 Sourcemap 无关, 在遍历时跳过
+    #[serde(flatten)]
+    children: BTreeMap<String, Value>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum ProjectPath {
+    Required(String),
+    Optional { optional: String },
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct MetaFile {
+    class_name: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+struct ModelFile {
+    class_name: String,
+}
+
+/// 文件在 Rojo 中对应的实例类型
+#[derive(Clone, Copy)]
+enum FileKind {
+    Script(&'static str),
+    Project,
+    Model,
+    Plain(&'static str),
+}
+
+/// 按文件名后缀确定实例名和类型, 与 Rojo 的同步规则一致; 无法同步的文件返回 None
+fn classify_file(file_name: &str) -> Option<(&str, FileKind)> {
+    const RULES: &[(&str, FileKind)] = &[
+        (".server.luau", FileKind::Script("Script")),
+        (".server.lua", FileKind::Script("Script")),
+        (".client.luau", FileKind::Script("LocalScript")),
+        (".client.lua", FileKind::Script("LocalScript")),
+        (".luau", FileKind::Script("ModuleScript")),
+        (".lua", FileKind::Script("ModuleScript")),
+        (".project.json", FileKind::Project),
+        (".model.json", FileKind::Model),
+        (".json", FileKind::Plain("ModuleScript")),
+        (".txt", FileKind::Plain("StringValue")),
+        (".csv", FileKind::Plain("LocalizationTable")),
+    ];
+
+    if file_name.ends_with(".meta.json") {
+        return None;
+    }
+
+    RULES.iter().find_map(|(suffix, kind)| {
+        let name = file_name.strip_suffix(suffix)?;
+        (!name.is_empty()).then_some((name, *kind))
+    })
+}
+
+/// 目录中作为目录本身脚本的 init 文件, 按 Rojo 的优先顺序
+const INIT_FILES: &[(&str, &str)] = &[
+    ("init.server.luau", "Script"),
+    ("init.server.lua", "Script"),
+    ("init.client.luau", "LocalScript"),
+    ("init.client.lua", "LocalScript"),
+    ("init.luau", "ModuleScript"),
+    ("init.lua", "ModuleScript"),
+];
+
+/// 不经过 rojo, 直接从项目文件生成与 `rojo sourcemap` 相同结构的 Sourcemap 树
+pub fn build_sourcemap_tree(project_file: &Path) -> Result<RojoSourceMapNode, Box<dyn Error>> {
+    let mut builder = Builder::default();
+    builder.project_node(project_file, None)
+}
+
+#[derive(Default)]
+struct Builder {
+    /// 已加载的项目文件, 用于检测循环引用
+    projects: Vec<PathBuf>,
+    /// 各项目文件的 `globIgnorePaths`, 相对于项目文件所在目录
+    ignore_patterns: Vec<(PathBuf, glob::Pattern)>,
+}
+
+impl Builder {
+    /// 加载项目文件; `name` 为空时使用项目名
+    fn project_node(
+        &mut self,
+        project_file: &Path,
+        name: Option<&str>,
+    ) -> Result<RojoSourceMapNode, Box<dyn Error>> {
+        let canonical = fs::canonicalize(project_file)
+            .map_err(|e| format!("无法读取项目文件 {}: {}", project_file.display(), e))?;
+        if self.projects.contains(&canonical) {
+            return Err(format!("项目文件被循环引用: {}", project_file.display()).into());
+        }
+
+        let contents = fs::read_to_string(project_file)?;
+        let project: ProjectFile = serde_json::from_str(&contents)
+            .map_err(|e| format!("解析项目文件 {} 失败: {}", project_file.display(), e))?;
+
+        let base_dir = project_file.parent().unwrap_or(Path::new("")).to_path_buf();
+        for pattern in &project.glob_ignore_paths {
+            let pattern = glob::Pattern::new(pattern)
+                .map_err(|e| format!("globIgnorePaths 中的 {} 无效: {}", pattern, e))?;
+            self.ignore_patterns.push((base_dir.clone(), pattern));
+        }
+
+        self.projects.push(canonical);
+        let result = self.tree_node(name.unwrap_or(&project.name), &project.tree, &base_dir);
+        self.projects.pop();
+
+        let mut node = result?;
+        node.filePaths.push(path_string(project_file));
+        Ok(node)
+    }
+
+    /// 项目文件 `tree` 中的节点: `$path` 指向的文件或目录, 加上树中声明的子节点
+    fn tree_node(
+        &mut self,
+        name: &str,
+        node: &ProjectNode,
+        base_dir: &Path,
+    ) -> Result<RojoSourceMapNode, Box<dyn Error>> {
+        let from_path = match &node.path {
+            Some(ProjectPath::Required(path)) => {
+                let path = base_dir.join(path);
+                let snapshot = self.path_node(name, &path)?;
+                Some(
+                    snapshot
+                        .ok_or_else(|| format!("$path 指向的路径不存在: {}", path.display()))?,
+                )
+            }
+            Some(ProjectPath::Optional { optional }) => {
+                self.path_node(name, &base_dir.join(optional))?
+            }
+            None => None,
+        };
+
+        let mut result = from_path.unwrap_or_else(|| RojoSourceMapNode {
+            name: name.to_string(),
+            // 未声明 $className 的节点通常是以服务名命名的服务
+            className: node.class_name.clone().unwrap_or_else(|| name.to_string()),
+            filePaths: Vec::new(),
+            children: Vec::new(),
+        });
+        if let Some(class_name) = &node.class_name {
+            result.className = class_name.clone();
+        }
+
+        for (child_name, value) in &node.children {
+            if child_name.starts_with('$') {
+                continue;
+            }
+            let child: ProjectNode = serde_json::from_value(value.clone())
+                .map_err(|e| format!("项目节点 {} 无效: {}", child_name, e))?;
+            let child = self.tree_node(child_name, &child, base_dir)?;
+
+            // 树中声明的节点覆盖文件系统中的同名实例
+            result
+                .children
+                .retain(|existing| existing.name != child.name);
+            result.children.push(child);
+        }
+
+        Ok(result)
+    }
+
+    /// 文件系统中的路径对应的实例, 路径不存在或被忽略时返回 None
+    fn path_node(
+        &mut self,
+        name: &str,
+        path: &Path,
+    ) -> Result<Option<RojoSourceMapNode>, Box<dyn Error>> {
+        if self.is_ignored(path) {
+            return Ok(None);
+        }
+
+        if path.is_dir() {
+            return self.dir_node(name, path).map(Some);
+        }
+        if !path.is_file() {
+            return Ok(None);
+        }
+
+        let file_name = file_name(path);
+        let Some((_, kind)) = classify_file(&file_name) else {
+            return Ok(None);
+        };
+        self.file_node(name, path, kind).map(Some)
+    }
+
+    /// 目录: 包含 `default.project.json` 时作为嵌套项目, 包含 init 脚本时作为脚本, 否则为 Folder
+    fn dir_node(&mut self, name: &str, dir: &Path) -> Result<RojoSourceMapNode, Box<dyn Error>> {
+        let project_file = dir.join(DEFAULT_PROJECT_FILE);
+        if project_file.is_file() {
+            return self.project_node(&project_file, Some(name));
+        }
+
+        let mut node = RojoSourceMapNode {
+            name: name.to_string(),
+            className: "Folder".to_string(),
+            filePaths: Vec::new(),
+            children: Vec::new(),
+        };
+
+        if let Some((init_file, class_name)) = INIT_FILES
+            .iter()
+            .find(|(init_file, _)| dir.join(init_file).is_file())
+        {
+            node.className = class_name.to_string();
+            node.filePaths.push(path_string(&dir.join(init_file)));
+        }
+        self.apply_meta_file(&mut node, &dir.join("init.meta.json"))?;
+
+        let mut entries: Vec<PathBuf> = fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .collect();
+        entries.sort();
+
+        for entry in entries {
+            let entry_name = file_name(&entry);
+            if entry_name.starts_with("init.") || self.is_ignored(&entry) {
+                continue;
+            }
+
+            let child = if entry.is_dir() {
+                self.path_node(&entry_name, &entry)?
+            } else {
+                match classify_file(&entry_name) {
+                    Some((child_name, kind)) => {
+                        let child_name = child_name.to_string();
+                        Some(self.file_node(&child_name, &entry, kind)?)
+                    }
+                    None => None,
+                }
+            };
+            node.children.extend(child);
+        }
+
+        Ok(node)
+    }
+
+    /// 单个文件对应的实例, `name` 为去掉后缀后的实例名
+    fn file_node(
+        &mut self,
+        name: &str,
+        path: &Path,
+        kind: FileKind,
+    ) -> Result<RojoSourceMapNode, Box<dyn Error>> {
+        let class_name = match kind {
+            FileKind::Project => return self.project_node(path, Some(name)),
+            FileKind::Model => {
+                let model: ModelFile = serde_json::from_str(&fs::read_to_string(path)?)
+                    .map_err(|e| format!("解析模型文件 {} 失败: {}", path.display(), e))?;
+                model.class_name
+            }
+            FileKind::Script(class_name) | FileKind::Plain(class_name) => class_name.to_string(),
+        };
+
+        let mut node = RojoSourceMapNode {
+            name: name.to_string(),
+            className: class_name,
+            filePaths: vec![path_string(path)],
+            children: Vec::new(),
+        };
+
+        // x.server.lua 与 x.lua 共用 x.meta.json
+        let stem = file_name(path);
+        let stem = classify_file(&stem).map_or(stem.as_str(), |(stem, _)| stem);
+        self.apply_meta_file(
+            &mut node,
+            &path.with_file_name(format!("{}.meta.json", stem)),
+        )?;
+
+        Ok(node)
+    }
+
+    /// 读取 `*.meta.json`, 记录文件路径并应用其中的 `className`
+    fn apply_meta_file(
+        &self,
+        node: &mut RojoSourceMapNode,
+        meta_file: &Path,
+    ) -> Result<(), Box<dyn Error>> {
+        if !meta_file.is_file() {
+            return Ok(());
+        }
+
+        let meta: MetaFile = serde_json::from_str(&fs::read_to_string(meta_file)?)
+            .map_err(|e| format!("解析 {} 失败: {}", meta_file.display(), e))?;
+        // 只有没有 init 脚本的目录可以通过 meta 文件修改类名
+        if let Some(class_name) = meta.class_name {
+            if node.filePaths.is_empty() {
+                node.className = class_name;
+            }
+        }
+        node.filePaths.push(path_string(meta_file));
+        Ok(())
+    }
+
+    fn is_ignored(&self, path: &Path) -> bool {
+        self.ignore_patterns.iter().any(|(base_dir, pattern)| {
+            path.strip_prefix(base_dir)
+                .is_ok_and(|relative| pattern.matches(&path_string(relative)))
+        })
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// 与 rojo 输出一致, 路径统一使用 `/` 分隔
+fn path_string(path: &Path) -> String {
+    let path = path.strip_prefix(".").unwrap_or(path);
+    path.to_string_lossy().replace('\\', "/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 临时目录中的 Rojo 项目, 结束时删除
+    struct TempProject(PathBuf);
+
+    impl TempProject {
+        fn new(name: &str, files: &[(&str, &str)]) -> Self {
+            let dir = std::env::temp_dir().join(format!(
+                "jest-lua-companion-project-{}-{}",
+                name,
+                std::process::id()
+            ));
+            let _ = fs::remove_dir_all(&dir);
+            for (path, contents) in files {
+                let path = dir.join(path);
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(path, contents).unwrap();
+            }
+            TempProject(dir)
+        }
+
+        fn build(&self) -> Result<RojoSourceMapNode, Box<dyn Error>> {
+            build_sourcemap_tree(&self.0.join(DEFAULT_PROJECT_FILE))
+        }
+
+        /// 节点引用的文件, 相对于项目目录
+        fn file_paths(&self, node: &RojoSourceMapNode) -> Vec<String> {
+            let prefix = format!("{}/", path_string(&self.0));
+            node.filePaths
+                .iter()
+                .map(|path| path.strip_prefix(&prefix).unwrap_or(path).to_string())
+                .collect()
+        }
+    }
+
+    impl Drop for TempProject {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn child<'a>(node: &'a RojoSourceMapNode, path: &[&str]) -> &'a RojoSourceMapNode {
+        path.iter().fold(node, |node, name| {
+            node.children
+                .iter()
+                .find(|child| child.name == *name)
+                .unwrap_or_else(|| panic!("{} 下没有 {}", node.name, name))
+        })
+    }
+
+    fn child_names(node: &RojoSourceMapNode) -> Vec<&str> {
+        node.children
+            .iter()
+            .map(|child| child.name.as_str())
+            .collect()
+    }
+
+    const SRC_PROJECT: &str = r#"{ "name": "game", "tree": { "$path": "src" } }"#;
+
+    #[test]
+    fn init_scripts_turn_folders_into_scripts() {
+        let project = TempProject::new(
+            "init",
+            &[
+                (DEFAULT_PROJECT_FILE, SRC_PROJECT),
+                ("src/Util/init.lua", ""),
+                ("src/Util/helper.luau", ""),
+                ("src/Server/init.server.lua", ""),
+                ("src/Plain/a.lua", ""),
+            ],
+        );
+        let root = project.build().unwrap();
+
+        let util = child(&root, &["Util"]);
+        assert_eq!(util.className, "ModuleScript");
+        assert_eq!(project.file_paths(util), ["src/Util/init.lua"]);
+        assert_eq!(child_names(util), ["helper"]);
+
+        let server = child(&root, &["Server"]);
+        assert_eq!(server.className, "Script");
+        assert!(server.children.is_empty());
+
+        assert_eq!(child(&root, &["Plain"]).className, "Folder");
+    }
+
+    #[test]
+    fn init_meta_json_sets_folder_class_name() {
+        let project = TempProject::new(
+            "meta",
+            &[
+                (DEFAULT_PROJECT_FILE, SRC_PROJECT),
+                (
+                    "src/Settings/init.meta.json",
+                    r#"{ "className": "Configuration" }"#,
+                ),
+                ("src/Settings/value.txt", "1"),
+                ("src/Module/init.lua", ""),
+                ("src/Module/init.meta.json", r#"{ "className": "Folder" }"#),
+            ],
+        );
+        let root = project.build().unwrap();
+
+        let settings = child(&root, &["Settings"]);
+        assert_eq!(settings.className, "Configuration");
+        assert_eq!(
+            project.file_paths(settings),
+            ["src/Settings/init.meta.json"]
+        );
+        assert_eq!(child(settings, &["value"]).className, "StringValue");
+
+        // 有 init 脚本时类名由脚本决定
+        let module = child(&root, &["Module"]);
+        assert_eq!(module.className, "ModuleScript");
+        assert_eq!(
+            project.file_paths(module),
+            ["src/Module/init.lua", "src/Module/init.meta.json"]
+        );
+    }
+
+    #[test]
+    fn script_suffixes_set_class_name_and_instance_name() {
+        let project = TempProject::new(
+            "suffixes",
+            &[
+                (DEFAULT_PROJECT_FILE, SRC_PROJECT),
+                ("src/main.server.lua", ""),
+                ("src/ui.client.luau", ""),
+                ("src/os-lib.spec.lua", ""),
+                ("src/notes.md", ""),
+            ],
+        );
+        let root = project.build().unwrap();
+
+        assert_eq!(child_names(&root), ["main", "os-lib.spec", "ui"]);
+        assert_eq!(child(&root, &["main"]).className, "Script");
+        assert_eq!(child(&root, &["ui"]).className, "LocalScript");
+        let spec = child(&root, &["os-lib.spec"]);
+        assert_eq!(spec.className, "ModuleScript");
+        assert_eq!(project.file_paths(spec), ["src/os-lib.spec.lua"]);
+    }
+
+    #[test]
+    fn nested_project_files() {
+        let project = TempProject::new(
+            "nested",
+            &[
+                (
+                    DEFAULT_PROJECT_FILE,
+                    r#"{
+                        "name": "game",
+                        "tree": {
+                            "$className": "DataModel",
+                            "ReplicatedStorage": {
+                                "Inner": { "$path": "pkgs/inner" },
+                                "Shared": { "$path": "shared" }
+                            }
+                        }
+                    }"#,
+                ),
+                (
+                    "pkgs/inner/default.project.json",
+                    r#"{ "name": "inner", "tree": { "$path": "src" } }"#,
+                ),
+                ("pkgs/inner/src/init.lua", ""),
+                (
+                    "shared/lib.project.json",
+                    r#"{ "name": "ignored-name", "tree": { "$path": "../vendor/lib" } }"#,
+                ),
+                ("vendor/lib/a.lua", ""),
+            ],
+        );
+        let root = project.build().unwrap();
+        assert_eq!(root.className, "DataModel");
+
+        // 嵌套项目使用父项目中的键名, 而不是项目名
+        let inner = child(&root, &["ReplicatedStorage", "Inner"]);
+        assert_eq!(inner.className, "ModuleScript");
+        assert_eq!(
+            project.file_paths(inner),
+            ["pkgs/inner/src/init.lua", "pkgs/inner/default.project.json"]
+        );
+
+        let lib = child(&root, &["ReplicatedStorage", "Shared", "lib"]);
+        assert_eq!(child_names(lib), ["a"]);
+        assert_eq!(
+            project.file_paths(lib).last().map(String::as_str),
+            Some("shared/lib.project.json")
+        );
+    }
+
+    #[test]
+    fn glob_ignore_paths_skip_matching_files() {
+        let project = TempProject::new(
+            "ignore",
+            &[
+                (
+                    DEFAULT_PROJECT_FILE,
+                    r#"{
+                        "name": "game",
+                        "globIgnorePaths": ["src/**/*.spec.lua", "src/Secret"],
+                        "tree": { "$path": "src" }
+                    }"#,
+                ),
+                ("src/a.lua", ""),
+                ("src/a.spec.lua", ""),
+                ("src/Nested/b.spec.lua", ""),
+                ("src/Nested/b.lua", ""),
+                ("src/Secret/c.lua", ""),
+            ],
+        );
+        let root = project.build().unwrap();
+
+        assert_eq!(child_names(&root), ["Nested", "a"]);
+        assert_eq!(child_names(child(&root, &["Nested"])), ["b"]);
+    }
+
+    #[test]
+    fn optional_path_may_be_missing() {
+        let project = TempProject::new(
+            "optional",
+            &[(
+                DEFAULT_PROJECT_FILE,
+                r#"{
+                    "name": "game",
+                    "tree": {
+                        "$className": "DataModel",
+                        "Generated": {
+                            "$className": "Folder",
+                            "$path": { "optional": "generated" }
+                        }
+                    }
+                }"#,
+            )],
+        );
+        let root = project.build().unwrap();
+
+        let generated = child(&root, &["Generated"]);
+        assert_eq!(generated.className, "Folder");
+        assert!(generated.filePaths.is_empty());
+
+        // 必需的 $path 不存在时报错
+        let required = TempProject::new(
+            "required",
+            &[(
+                DEFAULT_PROJECT_FILE,
+                r#"{ "name": "game", "tree": { "$path": "generated" } }"#,
+            )],
+        );
+        assert!(required.build().is_err());
+    }
+}
@@ -1,8 +1,21 @@
-use regex::Regex;
+use console::style;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::error::Error;
-use std::process::Command;
+use std::fmt;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Output, Stdio};
+use std::time::{Duration, Instant};
+
+use crate::{
+    config::{Config, SourceMapCommand},
+    rojo_project,
+    sourcemap_trie::{self, Lookup, SourcemapTrie},
+};
+
+/// `rojoSourceMapCommand` 的默认超时秒数
+const DEFAULT_SOURCEMAP_COMMAND_TIMEOUT: u64 = 30;
 
 #[derive(Serialize, Deserialize, Debug)]
 #[allow(non_snake_case)]
@@ -24,323 +37,353 @@ pub struct RojoSourceMapNode {
     pub children: Vec<RojoSourceMapNode>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Debug)]
 pub struct RojoSourceMap {
-    #[serde(flatten)]
-    entries: HashMap<String, RojoSourceMapEntry>,
-    // 解析后的完整路径映射
-    #[serde(skip)]
-    path_map: HashMap<String, String>,
+    // 按实例名分段的路径索引
+    trie: SourcemapTrie,
+    // 树状结构根节点的名称, 即rojo项目名
+    root_name: Option<String>,
 }
 
 impl RojoSourceMap {
     pub fn new(json: &str) -> Result<Self, Box<dyn Error>> {
         // 先尝试解析为展平的格式
         if let Ok(map) = serde_json::from_str::<HashMap<String, RojoSourceMapEntry>>(json) {
-            let source_map = RojoSourceMap {
-                entries: map,
-                path_map: HashMap::new(),
-            };
-            return Ok(source_map);
+            // 展平格式的键以 `.` 连接, 无法区分名称中本身带 `.` 的实例
+            let mut trie = SourcemapTrie::new("");
+            for (key, entry) in map {
+                let id = key
+                    .split('.')
+                    .fold(sourcemap_trie::ROOT, |id, name| trie.insert_child(id, name));
+                let path = (!entry.path.is_empty()).then_some(entry.path);
+                trie.add_file_paths(id, path.into_iter().chain(entry.filePaths));
+            }
+            return Ok(RojoSourceMap {
+                trie,
+                root_name: None,
+            });
         }
 
         // 如果失败，则尝试解析为树状结构并遍历
         let root_node: RojoSourceMapNode = serde_json::from_str(json)?;
-        let mut path_map = HashMap::new();
-
-        // 递归遍历树状结构，构建路径映射
-        process_node(&root_node, "", &mut path_map);
-
-        Ok(RojoSourceMap {
-            entries: HashMap::new(), // 树状结构不使用entries
-            path_map,
-        })
+        Ok(Self::from_tree(&root_node))
     }
 
-    /// 获取DataModel路径对应的本地文件路径
-    pub fn get_file_path(&self, datamodel_path: &str) -> Option<String> {
-        let datamodel_path = datamodel_path.trim();
-
-        // 1. 首先直接查找完整路径
-        if let Some(path) = self.path_map.get(datamodel_path) {
-            return Some(path.clone());
-        }
+    /// 从树状结构构建
+    pub fn from_tree(root_node: &RojoSourceMapNode) -> Self {
+        let mut trie = SourcemapTrie::new(&root_node.name);
+        trie.add_file_paths(sourcemap_trie::ROOT, root_node.filePaths.iter().cloned());
 
-        // 2. 尝试添加.lua后缀查找
-        let path_with_lua = format!("{}.lua", datamodel_path);
-        if let Some(path) = self.path_map.get(&path_with_lua) {
-            return Some(path.clone());
+        // 递归遍历树状结构，构建路径索引
+        for child in &root_node.children {
+            process_node(child, sourcemap_trie::ROOT, &mut trie);
         }
 
-        // 3. 如果是旧格式，尝试从entries获取
-        if let Some(entry) = self.entries.get(datamodel_path) {
-            if !entry.path.is_empty() {
-                return Some(entry.path.clone());
-            }
-            if !entry.filePaths.is_empty() {
-                return Some(entry.filePaths[0].clone());
-            }
+        RojoSourceMap {
+            trie,
+            root_name: Some(root_node.name.clone()),
         }
+    }
 
-        // 4. 准备添加DataModel前缀
-        let datamodel_key_prefix = format!("DataModel.{}", datamodel_path);
-        if let Some(path) = self.path_map.get(&datamodel_key_prefix) {
-            return Some(path.clone());
-        }
-
-        // 5. 尝试添加根项目名称前缀
-        if let Some((root_name, _)) = self.path_map.iter().next() {
-            if root_name.contains('.') {
-                // 取根项目名称
-                let root_project = root_name.split('.').next().unwrap_or("");
-                if !root_project.is_empty() {
-                    let full_path = format!("{}.{}", root_project, datamodel_path);
-                    let full_path_lua = format!("{}.{}.lua", root_project, datamodel_path);
-
-                    if let Some(path) = self.path_map.get(&full_path) {
-                        return Some(path.clone());
-                    }
-
-                    if let Some(path) = self.path_map.get(&full_path_lua) {
-                        return Some(path.clone());
-                    }
-                }
-            }
-        }
-
-        // 6. 处理部分匹配
-        let path_parts: Vec<&str> = datamodel_path.split('.').collect();
-        if path_parts.len() >= 2 {
-            let target_part = path_parts[path_parts.len() - 1]; // 最后一部分
-            let parent_part = path_parts[path_parts.len() - 2]; // 倒数第二部分
-
-            // 查找同时包含这两部分的路径
-            for (key, path) in &self.path_map {
-                let key_parts: Vec<&str> = key.split('.').collect();
-
-                // 查找键中是否包含目标部分和父部分
-                let contains_target = key_parts.iter().any(|&part| part == target_part);
-                let contains_parent = key_parts.iter().any(|&part| part == parent_part);
-
-                if contains_target && contains_parent {
-                    return Some(path.clone());
-                }
-            }
+    /// rojo项目名
+    pub fn project_name(&self) -> Option<&str> {
+        self.root_name.as_deref()
+    }
 
-            // 只查找关键部分
-            for (key, path) in &self.path_map {
-                if key.contains(target_part) {
-                    return Some(path.clone());
-                }
-            }
-        }
+    /// Sourcemap 中引用的所有本地文件路径
+    pub fn file_paths(&self) -> Vec<&str> {
+        self.trie.file_paths().collect()
+    }
 
-        None
+    /// 查找DataModel路径对应的本地文件, 见 `SourcemapTrie::lookup`
+    pub fn lookup(&self, datamodel_path: &str) -> Lookup<'_> {
+        self.trie.lookup(datamodel_path)
     }
 }
 
-/// 递归处理源码映射树节点，构建DataModel路径到本地文件路径的映射
-fn process_node(
-    node: &RojoSourceMapNode,
-    parent_path: &str,
-    path_map: &mut HashMap<String, String>,
-) {
-    // 构建当前节点的完整路径
-    let current_path = if parent_path.is_empty() {
-        node.name.clone()
-    } else {
-        format!("{}.{}", parent_path, node.name)
-    };
-
-    // 如果节点有文件路径，添加到映射
-    if !node.filePaths.is_empty() {
-        path_map.insert(current_path.clone(), node.filePaths[0].clone());
-    }
+/// 递归处理源码映射树节点，将每个实例按名称插入路径索引
+fn process_node(node: &RojoSourceMapNode, parent: usize, trie: &mut SourcemapTrie) {
+    let id = trie.insert_child(parent, &node.name);
+    trie.add_file_paths(id, node.filePaths.iter().cloned());
 
     // 递归处理子节点
     for child in &node.children {
-        process_node(child, &current_path, path_map);
+        process_node(child, id, trie);
     }
 }
 
-/// 获取 Rojo Sourcemap
-pub fn get_sourcemap(
-    command: &str,
-    config_dir: Option<&str>,
-) -> Result<RojoSourceMap, Box<dyn Error>> {
-    
-
-    // 创建一个默认的空sourcemap作为fallback
-    let empty_sourcemap = r#"{"entries":{}}"#;
-
-    // 尝试几种不同的方法执行命令
-    let mut sourcemap_json = String::new();
-    let mut success = false;
-
-    // 方法1: 使用powershell.exe
-    if !success {
-        let mut cmd = Command::new("powershell.exe");
-        cmd.args([
-            "-NoProfile",
-            "-ExecutionPolicy",
-            "Bypass",
-            "-Command",
+/// 加载 Sourcemap 的 JSON: 配置了 `rojoSourceMapCommand` 时执行该命令, 否则直接从 Rojo 项目文件生成
+///
+/// 返回的 JSON 已确认可以被 `RojoSourceMap::new` 解析; 通常应通过 `SourcemapCache` 调用
+pub fn load_sourcemap_json(config: &Config, debug: bool) -> Option<String> {
+    let Some(command) = &config.rojo_source_map_command else {
+        return build_native_sourcemap(config, debug);
+    };
+
+    let cwd = config
+        .rojo_source_map_cwd
+        .as_deref()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("."));
+    let timeout = Duration::from_secs(
+        config
+            .rojo_source_map_timeout
+            .unwrap_or(DEFAULT_SOURCEMAP_COMMAND_TIMEOUT),
+    );
+
+    if debug {
+        eprintln!(
+            "Sourcemap command: {} (工作目录: {})",
             command,
-        ]);
+            cwd.display()
+        );
+    }
 
-        if let Some(dir) = config_dir {
-            cmd.current_dir(dir);
+    match get_sourcemap(command, &cwd, timeout) {
+        Ok(sm) => Some(sm),
+        Err(e) => {
+            eprintln!("{}", style(format!("获取Rojo Sourcemap失败: {}", e)).red());
+            fallback_sourcemap(config, &cwd, debug)
         }
+    }
+}
 
-        match cmd.output() {
-            Ok(output) => {
-                if output.status.success() {
-                    sourcemap_json = String::from_utf8_lossy(&output.stdout).to_string();
-                    success = true;
-                } else {
-                    let error = String::from_utf8_lossy(&output.stderr);
-                    eprintln!("powershell.exe 方法失败: {}", error);
-                }
-            }
-            Err(e) => {
-                eprintln!("powershell.exe 执行错误: {}", e);
+/// `rojoSourceMapCommand` 失败后, 依次尝试从项目文件生成和已有的 sourcemap 文件
+fn fallback_sourcemap(config: &Config, cwd: &Path, debug: bool) -> Option<String> {
+    if let Some(sm) = build_native_sourcemap(config, debug) {
+        eprintln!("{}", style("已改用Rojo项目文件生成的Sourcemap").yellow());
+        return Some(sm);
+    }
+
+    let cache_paths = [
+        "sourcemap.json",
+        ".robloxrc/sourcemap.json",
+        ".rojo/sourcemap.json",
+    ];
+    for path in cache_paths {
+        let full_path = cwd.join(path);
+        let Ok(content) = std::fs::read_to_string(&full_path) else {
+            continue;
+        };
+        let json = strip_non_json_prefix(&content);
+        match RojoSourceMap::new(json) {
+            Ok(_) => {
+                eprintln!(
+                    "{}",
+                    style(format!("已改用 {} 中的Sourcemap", full_path.display())).yellow()
+                );
+                return Some(json.to_string());
             }
+            Err(e) => eprintln!(
+                "{}",
+                style(format!("解析 {} 失败: {}", full_path.display(), e)).yellow()
+            ),
         }
     }
 
-    // 方法2: 使用cmd.exe /c powershell
-    if !success {
-        let cmd_str = format!(
-            "powershell.exe -NoProfile -ExecutionPolicy Bypass -Command \"{}\"",
-            command.replace("\"", "\\\"")
-        );
-        eprintln!("尝试cmd.exe方法: {}", cmd_str);
-
-        let mut cmd = Command::new("cmd.exe");
-        cmd.args(["/c", &cmd_str]);
+    eprintln!(
+        "{}",
+        style("没有可用的Sourcemap, 堆栈信息不会转换为本地路径").yellow()
+    );
+    None
+}
 
-        if let Some(dir) = config_dir {
-            cmd.current_dir(dir);
+/// 不经过 rojo, 直接从 `rojoProjectFile` (默认 `default.project.json`) 生成 Sourcemap
+pub fn build_native_sourcemap(config: &Config, debug: bool) -> Option<String> {
+    let project_file = Path::new(
+        config
+            .rojo_project_file
+            .as_deref()
+            .unwrap_or(rojo_project::DEFAULT_PROJECT_FILE),
+    );
+
+    if !project_file.is_file() {
+        if debug || config.rojo_project_file.is_some() {
+            eprintln!(
+                "{}",
+                style(format!(
+                    "未找到Rojo项目文件 {}, 堆栈信息不会转换为本地路径",
+                    project_file.display()
+                ))
+                .yellow()
+            );
         }
+        return None;
+    }
 
-        match cmd.output() {
-            Ok(output) => {
-                if output.status.success() {
-                    sourcemap_json = String::from_utf8_lossy(&output.stdout).to_string();
-                    success = true;
-                } else {
-                    let error = String::from_utf8_lossy(&output.stderr);
-                    eprintln!("cmd.exe 方法失败: {}", error);
-                }
-            }
-            Err(e) => {
-                eprintln!("cmd.exe 执行错误: {}", e);
+    match rojo_project::build_sourcemap_tree(project_file) {
+        Ok(root_node) => {
+            if debug {
+                eprintln!(
+                    "{}",
+                    style(format!("已从 {} 生成Sourcemap", project_file.display())).dim()
+                );
             }
+            serde_json::to_string(&root_node).ok()
+        }
+        Err(e) => {
+            eprintln!(
+                "{}",
+                style(format!(
+                    "从 {} 生成Sourcemap失败: {}",
+                    project_file.display(),
+                    e
+                ))
+                .red()
+            );
+            None
         }
     }
+}
+
+/// 执行 `rojoSourceMapCommand` 失败的原因
+#[derive(Debug)]
+pub enum SourcemapCommandError {
+    /// 无法启动命令
+    Spawn { program: String, error: io::Error },
+    /// 超时未结束, 已被终止
+    Timeout(Duration),
+    /// 以非零状态退出
+    Failed { status: ExitStatus, stderr: String },
+    /// 标准输出不是有效的 Sourcemap
+    InvalidOutput(Box<dyn Error>),
+}
 
-    // 如果命令执行失败，尝试寻找缓存的sourcemap文件
-    if !success {
-        eprintln!("尝试查找缓存的sourcemap文件");
-        let cache_paths = [
-            "sourcemap.json",
-            ".robloxrc/sourcemap.json",
-            ".rojo/sourcemap.json",
-        ];
-
-        let base_dir = config_dir.unwrap_or(".");
-
-        for path in cache_paths.iter() {
-            let full_path = std::path::Path::new(base_dir).join(path);
-            eprintln!("检查缓存文件: {:?}", full_path);
-
-            if let Ok(content) = std::fs::read_to_string(&full_path) {
-                sourcemap_json = content;
-                success = true;
-                eprintln!("找到缓存的sourcemap: {:?}", full_path);
-                break;
+impl fmt::Display for SourcemapCommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SourcemapCommandError::Spawn { program, error } => {
+                write!(f, "无法启动 {}: {}", program, error)
+            }
+            SourcemapCommandError::Timeout(timeout) => {
+                write!(f, "命令在{}秒内没有结束, 已终止", timeout.as_secs())
+            }
+            SourcemapCommandError::Failed { status, stderr } => {
+                match status.code() {
+                    Some(code) => write!(f, "命令以退出码 {} 结束", code)?,
+                    None => write!(f, "命令被信号终止 ({})", status)?,
+                }
+                let stderr = stderr.trim();
+                if !stderr.is_empty() {
+                    write!(f, ", stderr:\n{}", stderr)?;
+                }
+                Ok(())
+            }
+            SourcemapCommandError::InvalidOutput(e) => {
+                write!(f, "命令输出不是有效的Sourcemap: {}", e)
             }
         }
     }
+}
 
-    // 如果所有方法都失败，使用空的sourcemap
-    if !success {
-        eprintln!("所有方法都失败，使用空的sourcemap");
-        sourcemap_json = empty_sourcemap.to_string();
-    }
-
-    // 移除可能存在的非JSON输出前缀
-    let json_start = sourcemap_json.find('{').unwrap_or(0);
-    let clean_json = &sourcemap_json[json_start..];
+impl Error for SourcemapCommandError {}
 
-    // 尝试解析JSON
-    match RojoSourceMap::new(clean_json) {
-        Ok(map) => Ok(map),
-        Err(e) => {
-            eprintln!("解析sourcemap失败: {}", e);
-            // 如果JSON解析失败，使用空的sourcemap
-            RojoSourceMap::new(empty_sourcemap)
+/// 执行 `rojoSourceMapCommand`, 返回其标准输出中的 Sourcemap JSON
+///
+/// 字符串形式通过系统 shell 执行 (Windows 上为 powershell, 其他平台为 `sh -c`), 数组形式直接执行, 不经过 shell
+pub fn get_sourcemap(
+    command: &SourceMapCommand,
+    cwd: &Path,
+    timeout: Duration,
+) -> Result<String, SourcemapCommandError> {
+    let (program, args) = match command {
+        SourceMapCommand::Shell(command) if cfg!(windows) => (
+            "powershell.exe".to_string(),
+            vec![
+                "-NoProfile".to_string(),
+                "-ExecutionPolicy".to_string(),
+                "Bypass".to_string(),
+                "-Command".to_string(),
+                command.clone(),
+            ],
+        ),
+        SourceMapCommand::Shell(command) => {
+            ("sh".to_string(), vec!["-c".to_string(), command.clone()])
         }
+        SourceMapCommand::Args(argv) => match argv.split_first() {
+            Some((program, args)) => (program.clone(), args.to_vec()),
+            None => {
+                return Err(SourcemapCommandError::Spawn {
+                    program: String::new(),
+                    error: io::Error::new(io::ErrorKind::InvalidInput, "命令为空"),
+                })
+            }
+        },
+    };
+
+    let mut cmd = Command::new(&program);
+    cmd.args(&args)
+        .current_dir(cwd)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    let output = run_with_timeout(cmd, timeout).map_err(|e| match e {
+        RunError::Spawn(error) => SourcemapCommandError::Spawn { program, error },
+        RunError::Timeout => SourcemapCommandError::Timeout(timeout),
+    })?;
+
+    if !output.status.success() {
+        return Err(SourcemapCommandError::Failed {
+            status: output.status,
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        });
     }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let json = strip_non_json_prefix(&stdout);
+    RojoSourceMap::new(json).map_err(SourcemapCommandError::InvalidOutput)?;
+    Ok(json.to_string())
 }
 
-/// 将 Roblox 堆栈跟踪行转换为本地文件路径
-pub fn convert_stack_trace(line: &str, sourcemap: &RojoSourceMap) -> String {
-    // 示例: ServerScriptService.TS.Runtime.__tests__.os-lib.jack:58
-    let re = Regex::new(r"(.+?):(\d+)").unwrap();
-
-    if let Some(captures) = re.captures(line) {
-        let datamodel_path = captures.get(1).unwrap().as_str();
-        let line_number = captures.get(2).unwrap().as_str();
-
-        // 尝试在sourcemap中查找对应的本地路径
-        if let Some(file_path) = sourcemap.get_file_path(datamodel_path) {
-            return format!("{}:{}", file_path, line_number);
-        } else {
-            // 额外尝试特殊处理
-            // 检查是否包含特殊字符@
-            if datamodel_path.contains('@') {
-                let simplified_path = datamodel_path.replace("@", "");
-
-                // 尝试在路径映射中查找简化后的路径
-                for (key, path) in &sourcemap.path_map {
-                    let simplified_key = key.replace("@", "");
-
-                    // 如果简化后的键包含简化后的路径
-                    if simplified_key.contains(&simplified_path) {
-                        return format!("{}:{}", path, line_number);
-                    }
-                }
+enum RunError {
+    Spawn(io::Error),
+    Timeout,
+}
 
-                // 尝试按路径部分匹配
-                let parts: Vec<&str> = datamodel_path.split('.').collect();
-                if !parts.is_empty() {
-                    let last_part = parts[parts.len() - 1];
-
-                    // 查找包含最后一部分的路径
-                    for (key, path) in &sourcemap.path_map {
-                        if key.contains(last_part) {
-                            return format!("{}:{}", path, line_number);
-                        }
-                    }
-                }
+/// 执行命令并收集输出, 超时后终止进程
+fn run_with_timeout(mut cmd: Command, timeout: Duration) -> Result<Output, RunError> {
+    let mut child = cmd.spawn().map_err(RunError::Spawn)?;
+
+    // 在单独的线程中读取输出, 避免管道写满导致子进程阻塞
+    let stdout = child.stdout.take().map(read_in_background);
+    let stderr = child.stderr.take().map(read_in_background);
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        match child.try_wait().map_err(RunError::Spawn)? {
+            Some(status) => break status,
+            None if Instant::now() >= deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(RunError::Timeout);
             }
+            None => std::thread::sleep(Duration::from_millis(20)),
         }
-    }
+    };
 
-    // 如果找不到对应路径，返回原始行
-    line.to_string()
+    let collect = |handle: Option<std::thread::JoinHandle<Vec<u8>>>| {
+        handle
+            .and_then(|handle| handle.join().ok())
+            .unwrap_or_default()
+    };
+    Ok(Output {
+        status,
+        stdout: collect(stdout),
+        stderr: collect(stderr),
+    })
 }
 
-/// 转换多行堆栈信息
-pub fn convert_stack_trace_text(text: &str, sourcemap: &RojoSourceMap) -> String {
-    let lines: Vec<&str> = text.split('\n').collect();
-    let mut result = Vec::with_capacity(lines.len());
-
-    for line in lines {
-        let converted_line = convert_stack_trace(line, sourcemap);
-        result.push(converted_line);
-    }
+fn read_in_background(mut reader: impl Read + Send + 'static) -> std::thread::JoinHandle<Vec<u8>> {
+    std::thread::spawn(move || {
+        let mut buf = Vec::new();
+        let _ = reader.read_to_end(&mut buf);
+        buf
+    })
+}
 
-    result.join("\n")
+/// 移除可能存在的非JSON输出前缀
+fn strip_non_json_prefix(output: &str) -> &str {
+    let json_start = output.find('{').unwrap_or(0);
+    &output[json_start..]
 }
@@ -0,0 +1,121 @@
+use crate::exit_code::ExitCode;
+use axum::Router;
+use axum_server::Handle;
+use console::style;
+use serde::{Deserialize, Serialize};
+use std::{
+    io,
+    net::{SocketAddr, TcpListener, ToSocketAddrs},
+    path::PathBuf,
+    time::Duration,
+};
+use tokio::sync::mpsc::UnboundedReceiver;
+
+/// 默认监听地址, 与插件中的 `HOST` 一致
+pub const DEFAULT_HOST: &str = "127.0.0.1";
+/// 默认端口, 与插件中的 `BASE_PORT` 一致
+pub const DEFAULT_PORT: u16 = 28860;
+/// 端口被占用时依次尝试的端口数量, 与插件中的 `PORT_RANGE` 一致
+pub const PORT_RANGE: u16 = 10;
+/// 优雅关闭时等待进行中的请求完成的最长时间
+const GRACEFUL_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// 写入发现文件的服务信息, 供插件以外的工具查找正在运行的实例
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DiscoveryInfo {
+    pub host: String,
+    pub port: u16,
+    pub url: String,
+    pub pid: u32,
+    pub cwd: Option<PathBuf>,
+}
+
+/// 绑定监听端口, 端口被占用时依次尝试之后的 `PORT_RANGE - 1` 个端口
+pub fn bind_listener(host: &str, port: u16) -> io::Result<TcpListener> {
+    let mut last_error = None;
+
+    for candidate in port..port.saturating_add(PORT_RANGE) {
+        let addrs: Vec<SocketAddr> = (host, candidate).to_socket_addrs()?.collect();
+        match TcpListener::bind(&addrs[..]) {
+            Ok(listener) => {
+                if candidate != port {
+                    eprintln!(
+                        "{}",
+                        style(format!("端口 {} 已被占用, 改用端口 {}", port, candidate)).yellow()
+                    );
+                }
+                return Ok(listener);
+            }
+            Err(e) if e.kind() == io::ErrorKind::AddrInUse => last_error = Some(e),
+            Err(e) => return Err(e),
+        }
+    }
+
+    Err(last_error.unwrap_or_else(|| io::Error::from(io::ErrorKind::AddrInUse)))
+}
+
+/// 发现文件所在目录: `<系统临时目录>/jest-lua-companion`
+pub fn discovery_dir() -> PathBuf {
+    std::env::temp_dir().join("jest-lua-companion")
+}
+
+/// 当前实例的发现文件路径, 按端口区分: `<discovery_dir>/<port>.json`
+pub fn discovery_file(port: u16) -> PathBuf {
+    discovery_dir().join(format!("{}.json", port))
+}
+
+/// 写入发现文件, 返回文件路径
+pub fn write_discovery_file(addr: SocketAddr) -> io::Result<PathBuf> {
+    let info = DiscoveryInfo {
+        host: addr.ip().to_string(),
+        port: addr.port(),
+        url: format!("http://{}", addr),
+        pid: std::process::id(),
+        cwd: std::env::current_dir().ok(),
+    };
+
+    std::fs::create_dir_all(discovery_dir())?;
+    let path = discovery_file(addr.port());
+    std::fs::write(&path, serde_json::to_string_pretty(&info)?)?;
+    Ok(path)
+}
+
+/// 删除发现文件, 服务器关闭时调用
+pub fn remove_discovery_file(port: u16) -> io::Result<()> {
+    match std::fs::remove_file(discovery_file(port)) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// 运行HTTP服务器, 直到收到退出码或 Ctrl+C, 然后等待进行中的请求完成后返回退出码
+pub async fn serve(
+    app: Router,
+    listener: TcpListener,
+    mut shutdown: UnboundedReceiver<ExitCode>,
+) -> io::Result<ExitCode> {
+    listener.set_nonblocking(true)?;
+
+    let handle = Handle::new();
+    let server = axum_server::from_tcp(listener)
+        .handle(handle.clone())
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>());
+    tokio::pin!(server);
+
+    let exit_code = tokio::select! {
+        result = &mut server => {
+            // 服务器不会自行结束, 走到这里说明出错了
+            result?;
+            return Ok(ExitCode::ConfigError);
+        }
+        exit_code = shutdown.recv() => exit_code.unwrap_or(ExitCode::Success),
+        _ = tokio::signal::ctrl_c() => {
+            eprintln!("{}", style("收到 Ctrl+C, 正在关闭服务器...").yellow());
+            ExitCode::Interrupted
+        }
+    };
+
+    handle.graceful_shutdown(Some(GRACEFUL_SHUTDOWN_TIMEOUT));
+    server.await?;
+    Ok(exit_code)
+}
@@ -0,0 +1,208 @@
+use console::style;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeSet},
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    config::Config,
+    rojo_project,
+    rojo_sourcemap::{load_sourcemap_json, RojoSourceMap},
+    server,
+};
+
+/// 内存和磁盘中的 Sourcemap 缓存
+///
+/// 以项目文件内容、Sourcemap 命令以及所有引用路径的修改时间计算指纹, 指纹不变时直接复用,
+/// 避免每次收到测试结果都重新执行 rojo 并解析整棵树
+pub struct SourcemapCache {
+    /// `--no-sourcemap-cache` 时为 false, 每次都重新生成
+    enabled: bool,
+    /// 只在读写时短暂加锁, 检查指纹和生成 Sourcemap 都在锁外进行, 不会让并发的请求排队等待 rojo
+    cached: Mutex<Option<Arc<CachedSourcemap>>>,
+}
+
+struct CachedSourcemap {
+    fingerprint: u64,
+    paths: Vec<String>,
+    sourcemap: Arc<RojoSourceMap>,
+}
+
+/// 磁盘缓存文件的内容
+#[derive(Serialize, Deserialize)]
+struct CacheFile {
+    fingerprint: u64,
+    /// 计算指纹时检查修改时间的路径
+    paths: Vec<String>,
+    /// Sourcemap JSON, 与 `rojo sourcemap` 的输出格式相同
+    sourcemap: String,
+}
+
+impl SourcemapCache {
+    pub fn new(enabled: bool) -> Self {
+        SourcemapCache {
+            enabled,
+            cached: Mutex::new(None),
+        }
+    }
+
+    /// 获取 Sourcemap, 依次使用内存缓存、磁盘缓存, 都失效时重新生成
+    pub fn load(&self, config: &Config, debug: bool) -> Option<Arc<RojoSourceMap>> {
+        if self.enabled {
+            let cached = self.cached.lock().unwrap().clone();
+            if let Some(entry) = cached {
+                if fingerprint(config, &entry.paths) == entry.fingerprint {
+                    return Some(entry.sourcemap.clone());
+                }
+            }
+
+            if let Some(entry) = read_cache_file(config, debug) {
+                let sourcemap = entry.sourcemap.clone();
+                self.store(entry);
+                return Some(sourcemap);
+            }
+        }
+
+        self.regenerate(config, debug)
+    }
+
+    /// 跳过缓存重新生成 Sourcemap
+    ///
+    /// 指纹只检查已引用文件的各级目录, 新建的空目录中再添加文件时不会失效, 监听到文件变更时使用
+    pub fn regenerate(&self, config: &Config, debug: bool) -> Option<Arc<RojoSourceMap>> {
+        let json = load_sourcemap_json(config, debug)?;
+        let sourcemap = Arc::new(RojoSourceMap::new(&json).ok()?);
+        let paths = watched_paths(config, &sourcemap);
+        let entry = CachedSourcemap {
+            fingerprint: fingerprint(config, &paths),
+            paths,
+            sourcemap: sourcemap.clone(),
+        };
+
+        if let Err(e) = write_cache_file(&entry, json) {
+            if debug {
+                eprintln!(
+                    "{}",
+                    style(format!("写入Sourcemap缓存失败: {}", e)).yellow()
+                );
+            }
+        }
+        self.store(entry);
+        Some(sourcemap)
+    }
+
+    fn store(&self, entry: CachedSourcemap) {
+        self.cached.lock().unwrap().replace(Arc::new(entry));
+    }
+}
+
+/// 当前目录对应的磁盘缓存文件: `<discovery_dir>/sourcemap-<当前目录的哈希>.json`
+fn cache_file() -> PathBuf {
+    let cwd = std::env::current_dir()
+        .and_then(|cwd| cwd.canonicalize())
+        .unwrap_or_default();
+    let mut hasher = DefaultHasher::new();
+    cwd.hash(&mut hasher);
+    server::discovery_dir().join(format!("sourcemap-{:016x}.json", hasher.finish()))
+}
+
+fn read_cache_file(config: &Config, debug: bool) -> Option<CachedSourcemap> {
+    let path = cache_file();
+    let contents = std::fs::read_to_string(&path).ok()?;
+    let file: CacheFile = serde_json::from_str(&contents).ok()?;
+    if fingerprint(config, &file.paths) != file.fingerprint {
+        return None;
+    }
+
+    let sourcemap = RojoSourceMap::new(&file.sourcemap).ok()?;
+    if debug {
+        eprintln!(
+            "{}",
+            style(format!("使用Sourcemap缓存 {}", path.display())).dim()
+        );
+    }
+    Some(CachedSourcemap {
+        fingerprint: file.fingerprint,
+        paths: file.paths,
+        sourcemap: Arc::new(sourcemap),
+    })
+}
+
+fn write_cache_file(entry: &CachedSourcemap, sourcemap: String) -> std::io::Result<()> {
+    let file = CacheFile {
+        fingerprint: entry.fingerprint,
+        paths: entry.paths.clone(),
+        sourcemap,
+    };
+    std::fs::create_dir_all(server::discovery_dir())?;
+    std::fs::write(cache_file(), serde_json::to_string(&file)?)
+}
+
+/// 影响 Sourcemap 内容的路径: 项目文件、引用的文件以及它们所在的各级目录(目录的修改时间反映文件的增删)
+fn watched_paths(config: &Config, sourcemap: &RojoSourceMap) -> Vec<String> {
+    let base_dir = config.sourcemap_base_dir();
+
+    let mut paths = BTreeSet::new();
+    paths.insert(project_file(config).to_string_lossy().into_owned());
+
+    for file_path in sourcemap.file_paths() {
+        let file_path = Path::new(file_path);
+        paths.insert(base_dir.join(file_path).to_string_lossy().into_owned());
+        for dir in source_dirs(file_path) {
+            paths.insert(base_dir.join(dir).to_string_lossy().into_owned());
+        }
+    }
+
+    paths.into_iter().collect()
+}
+
+/// 文件所在的各级目录, 直到 `$path` 根目录; 只包含子文件夹的目录中增删子文件夹时, 只有它自己的修改时间会变化
+///
+/// 不包含基准目录本身(报告文件等会写入其中), 绝对路径只包含直接所在的目录
+fn source_dirs(file_path: &Path) -> Vec<&Path> {
+    if file_path.is_absolute() {
+        return file_path.parent().into_iter().collect();
+    }
+    file_path
+        .ancestors()
+        .skip(1)
+        .take_while(|dir| dir.file_name().is_some())
+        .collect()
+}
+
+/// Rojo 项目文件; 配置了 `rojoSourceMapCommand` 时 rojo 在 `rojoSourceMapCwd` 中读取它
+fn project_file(config: &Config) -> PathBuf {
+    config.sourcemap_base_dir().join(
+        config
+            .rojo_project_file
+            .as_deref()
+            .unwrap_or(rojo_project::DEFAULT_PROJECT_FILE),
+    )
+}
+
+/// 计算缓存指纹, 任一输入变化都会使缓存失效
+fn fingerprint(config: &Config, paths: &[String]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    env!("CARGO_PKG_VERSION").hash(&mut hasher);
+    config
+        .rojo_source_map_command
+        .as_ref()
+        .map(ToString::to_string)
+        .hash(&mut hasher);
+    config.rojo_source_map_cwd.hash(&mut hasher);
+    std::fs::read(project_file(config)).ok().hash(&mut hasher);
+
+    for path in paths {
+        let path = if path.is_empty() { "." } else { path };
+        path.hash(&mut hasher);
+        std::fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .hash(&mut hasher);
+    }
+
+    hasher.finish()
+}
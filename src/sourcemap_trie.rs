@@ -0,0 +1,365 @@
+use std::collections::{BTreeMap, HashMap};
+
+/// 前缀树根节点的编号
+pub const ROOT: usize = 0;
+
+/// 按实例名分段保存的 DataModel 路径前缀树, 用于从堆栈中的路径查找本地文件
+#[derive(Debug)]
+pub struct SourcemapTrie {
+    nodes: Vec<TrieNode>,
+    /// 实例名到节点编号的索引, 用于后缀匹配
+    by_name: HashMap<String, Vec<usize>>,
+}
+
+#[derive(Debug)]
+struct TrieNode {
+    name: String,
+    parent: Option<usize>,
+    children: BTreeMap<String, usize>,
+    file_paths: Vec<String>,
+}
+
+/// 查找结果的可信度, 越靠后越可信
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Confidence {
+    /// 路径不完整, 只有末尾 `matched` 个实例与唯一的候选匹配
+    Suffix { matched: usize },
+    /// 路径从根节点(或其子节点, 即服务)开始完整匹配
+    Exact,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Match<'a> {
+    pub file_path: &'a str,
+    /// 匹配到的实例的完整 DataModel 路径
+    pub datamodel_path: String,
+    pub confidence: Confidence,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Lookup<'a> {
+    Found(Match<'a>),
+    /// 有多个同样可信的候选, 不做猜测
+    Ambiguous(Vec<Match<'a>>),
+    NotFound,
+}
+
+impl SourcemapTrie {
+    pub fn new(root_name: &str) -> Self {
+        SourcemapTrie {
+            nodes: vec![TrieNode {
+                name: root_name.to_string(),
+                parent: None,
+                children: BTreeMap::new(),
+                file_paths: Vec::new(),
+            }],
+            by_name: HashMap::new(),
+        }
+    }
+
+    /// 获取或创建 `parent` 下名为 `name` 的子节点
+    pub fn insert_child(&mut self, parent: usize, name: &str) -> usize {
+        if let Some(&id) = self.nodes[parent].children.get(name) {
+            return id;
+        }
+
+        let id = self.nodes.len();
+        self.nodes.push(TrieNode {
+            name: name.to_string(),
+            parent: Some(parent),
+            children: BTreeMap::new(),
+            file_paths: Vec::new(),
+        });
+        self.nodes[parent].children.insert(name.to_string(), id);
+        self.by_name.entry(name.to_string()).or_default().push(id);
+        id
+    }
+
+    pub fn add_file_paths(&mut self, id: usize, file_paths: impl IntoIterator<Item = String>) {
+        self.nodes[id].file_paths.extend(file_paths);
+    }
+
+    /// 所有节点引用的本地文件路径
+    pub fn file_paths(&self) -> impl Iterator<Item = &str> {
+        self.nodes
+            .iter()
+            .flat_map(|node| node.file_paths.iter().map(String::as_str))
+    }
+
+    /// 查找 DataModel 路径(以 `.` 连接)对应的本地文件
+    ///
+    /// 实例名本身可能带 `.` (如 `os-lib.spec`), 因此不按 `.` 拆分路径, 而是沿前缀树贪婪匹配最长的实例名.
+    /// 依次尝试: 从根节点完整匹配, 从服务开始完整匹配, 最长后缀匹配. 后缀匹配有多个同样长的候选时返回 `Ambiguous`
+    pub fn lookup(&self, path: &str) -> Lookup<'_> {
+        let path = path.trim();
+        if path.is_empty() {
+            return Lookup::NotFound;
+        }
+
+        let exact = path
+            .strip_prefix(self.nodes[ROOT].name.as_str())
+            .and_then(|rest| rest.strip_prefix('.'))
+            .and_then(|rest| self.walk(ROOT, rest))
+            .or_else(|| self.walk(ROOT, path));
+        if let Some(found) = exact.and_then(|id| self.to_match(id, Confidence::Exact)) {
+            return Lookup::Found(found);
+        }
+
+        // 末尾实例名可能是路径在任意 `.` 之后的部分
+        let last_names = std::iter::once(path)
+            .chain(path.match_indices('.').map(|(index, _)| &path[index + 1..]));
+
+        let mut best = (0, 0);
+        let mut candidates = Vec::new();
+        for name in last_names {
+            for &id in self.by_name.get(name).into_iter().flatten() {
+                if self.nodes[id].file_paths.is_empty() {
+                    continue;
+                }
+                let matched = self.matching_suffix(id, path);
+                if matched.1 > best.1 {
+                    best = matched;
+                    candidates.clear();
+                }
+                if matched.1 == best.1 {
+                    candidates.push(id);
+                }
+            }
+        }
+
+        let confidence = Confidence::Suffix { matched: best.0 };
+        let mut matches: Vec<Match> = candidates
+            .into_iter()
+            .filter_map(|id| self.to_match(id, confidence))
+            .collect();
+        match matches.len() {
+            0 => Lookup::NotFound,
+            1 => Lookup::Found(matches.remove(0)),
+            _ => {
+                matches.sort_by(|a, b| a.datamodel_path.cmp(&b.datamodel_path));
+                Lookup::Ambiguous(matches)
+            }
+        }
+    }
+
+    /// 从 `from` 开始沿路径向下查找, 每一步优先匹配最长的子节点名, 失败时回退
+    fn walk(&self, from: usize, path: &str) -> Option<usize> {
+        if path.is_empty() {
+            return Some(from);
+        }
+
+        let mut ends: Vec<usize> = path.match_indices('.').map(|(index, _)| index).collect();
+        ends.push(path.len());
+
+        ends.into_iter().rev().find_map(|end| {
+            let child = *self.nodes[from].children.get(&path[..end])?;
+            self.walk(child, path.get(end + 1..).unwrap_or(""))
+        })
+    }
+
+    /// 节点路径与 `path` 末尾相同的部分, 返回 (实例段数, 字符数)
+    fn matching_suffix(&self, id: usize, path: &str) -> (usize, usize) {
+        let mut node = Some(id);
+        let mut rest = path;
+        let mut matched = 0;
+
+        while let Some(current) = node {
+            let name = self.nodes[current].name.as_str();
+            if rest == name {
+                matched += 1;
+                rest = "";
+                break;
+            }
+            match rest
+                .strip_suffix(name)
+                .and_then(|prefix| prefix.strip_suffix('.'))
+            {
+                Some(prefix) => {
+                    matched += 1;
+                    rest = prefix;
+                    node = self.nodes[current].parent;
+                }
+                None => break,
+            }
+        }
+
+        (matched, path.len() - rest.len())
+    }
+
+    fn to_match(&self, id: usize, confidence: Confidence) -> Option<Match<'_>> {
+        Some(Match {
+            file_path: self.nodes[id].file_paths.first()?,
+            datamodel_path: self.datamodel_path(id),
+            confidence,
+        })
+    }
+
+    /// 节点的完整 DataModel 路径(不含根节点)
+    pub fn datamodel_path(&self, id: usize) -> String {
+        let mut names = Vec::new();
+        let mut node = id;
+        while let Some(parent) = self.nodes[node].parent {
+            names.push(self.nodes[node].name.as_str());
+            node = parent;
+        }
+        names.reverse();
+        names.join(".")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rojo_sourcemap::RojoSourceMap;
+
+    /// 以项目 `game-project` 为根, 按实例名列表插入节点
+    fn trie(entries: &[(&[&str], &str)]) -> SourcemapTrie {
+        let mut trie = SourcemapTrie::new("game-project");
+        for (names, file_path) in entries {
+            let id = names
+                .iter()
+                .fold(ROOT, |id, name| trie.insert_child(id, name));
+            trie.add_file_paths(id, [file_path.to_string()]);
+        }
+        trie
+    }
+
+    fn found<'a>(lookup: Lookup<'a>) -> Match<'a> {
+        match lookup {
+            Lookup::Found(found) => found,
+            other => panic!("未找到唯一匹配: {:?}", other),
+        }
+    }
+
+    fn server_trie() -> SourcemapTrie {
+        trie(&[
+            (
+                &["ServerScriptService", "Server", "os-lib.spec"],
+                "src/server/os-lib.spec.lua",
+            ),
+            (
+                &["ServerScriptService", "Server", "os-lib", "other"],
+                "src/server/os-lib/other.lua",
+            ),
+            (
+                &["ServerScriptService", "Server", "jack.lua"],
+                "src/server/jack.lua.luau",
+            ),
+        ])
+    }
+
+    #[test]
+    fn exact_match_of_dotted_instance_names() {
+        let trie = server_trie();
+
+        let spec = found(trie.lookup("game-project.ServerScriptService.Server.os-lib.spec"));
+        assert_eq!(spec.file_path, "src/server/os-lib.spec.lua");
+        assert_eq!(
+            spec.datamodel_path,
+            "ServerScriptService.Server.os-lib.spec"
+        );
+        assert_eq!(spec.confidence, Confidence::Exact);
+
+        let jack = found(trie.lookup("ServerScriptService.Server.jack.lua"));
+        assert_eq!(jack.file_path, "src/server/jack.lua.luau");
+        assert_eq!(jack.confidence, Confidence::Exact);
+    }
+
+    #[test]
+    fn dotted_name_competing_with_nested_path() {
+        // `os-lib.spec` 与 `os-lib` 下的子节点共享前缀, 最长的实例名匹配失败时回退到嵌套路径
+        let trie = server_trie();
+
+        let other = found(trie.lookup("ServerScriptService.Server.os-lib.other"));
+        assert_eq!(other.file_path, "src/server/os-lib/other.lua");
+        assert_eq!(other.confidence, Confidence::Exact);
+
+        let spec = found(trie.lookup("ServerScriptService.Server.os-lib.spec"));
+        assert_eq!(spec.file_path, "src/server/os-lib.spec.lua");
+    }
+
+    #[test]
+    fn suffix_match_prefers_longest_suffix() {
+        let trie = trie(&[
+            (
+                &["ReplicatedStorage", "Shared", "Util"],
+                "src/shared/Util.lua",
+            ),
+            (
+                &["ReplicatedStorage", "Packages", "Util"],
+                "Packages/Util.lua",
+            ),
+        ]);
+
+        // 堆栈中的路径缺少服务名, 或者开头的实例与 Sourcemap 不同
+        for path in ["Shared.Util", "Workspace.Shared.Util"] {
+            let util = found(trie.lookup(path));
+            assert_eq!(util.file_path, "src/shared/Util.lua");
+            assert_eq!(util.datamodel_path, "ReplicatedStorage.Shared.Util");
+            assert_eq!(util.confidence, Confidence::Suffix { matched: 2 });
+        }
+
+        assert_eq!(trie.lookup("Nope.Missing"), Lookup::NotFound);
+        assert_eq!(trie.lookup(""), Lookup::NotFound);
+    }
+
+    #[test]
+    fn equally_good_candidates_are_ambiguous() {
+        let trie = trie(&[
+            (&["ServerStorage", "Util"], "src/storage/Util.lua"),
+            (
+                &["ReplicatedStorage", "Shared", "Util"],
+                "src/shared/Util.lua",
+            ),
+            (
+                &["ReplicatedStorage", "Packages", "Util"],
+                "Packages/Util.lua",
+            ),
+        ]);
+
+        let Lookup::Ambiguous(candidates) = trie.lookup("Other.Util") else {
+            panic!("应有多个候选");
+        };
+        let candidates: Vec<(&str, &str)> = candidates
+            .iter()
+            .map(|candidate| (candidate.datamodel_path.as_str(), candidate.file_path))
+            .collect();
+        assert_eq!(
+            candidates,
+            [
+                ("ReplicatedStorage.Packages.Util", "Packages/Util.lua"),
+                ("ReplicatedStorage.Shared.Util", "src/shared/Util.lua"),
+                ("ServerStorage.Util", "src/storage/Util.lua"),
+            ]
+        );
+    }
+
+    #[test]
+    fn flat_sourcemap_format() {
+        let sourcemap = RojoSourceMap::new(
+            r#"{
+                "ServerScriptService.Server.main": { "path": "src/server/main.server.lua" },
+                "ReplicatedStorage.Shared.Util": { "filePaths": ["src/shared/Util.lua"] },
+                "ReplicatedStorage.Shared": {}
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(sourcemap.project_name(), None);
+
+        let main = found(sourcemap.lookup("ServerScriptService.Server.main"));
+        assert_eq!(main.file_path, "src/server/main.server.lua");
+        assert_eq!(main.confidence, Confidence::Exact);
+
+        let util = found(sourcemap.lookup("Shared.Util"));
+        assert_eq!(util.file_path, "src/shared/Util.lua");
+        assert_eq!(util.confidence, Confidence::Suffix { matched: 2 });
+
+        let mut file_paths = sourcemap.file_paths();
+        file_paths.sort();
+        assert_eq!(
+            file_paths,
+            ["src/server/main.server.lua", "src/shared/Util.lua"]
+        );
+    }
+}
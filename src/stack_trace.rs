@@ -0,0 +1,344 @@
+use regex::{Captures, Regex};
+use std::{ops::Range, sync::LazyLock};
+
+use crate::{file_links::FileLinks, rojo_sourcemap::RojoSourceMap, sourcemap_trie::Lookup};
+
+/// `Script 'ServerScriptService.Foo', Line 12 - function bar`
+static SCRIPT_LINE_FRAME: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^\s*(?P<location>Script '(?P<path>[^']+)', Line (?P<line>\d+))(?: - (?:function )?(?P<function>.+))?",
+    )
+    .unwrap()
+});
+
+/// `[string "ServerScriptService.Foo"]:12: message`
+static STRING_CHUNK_FRAME: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"^\s*(?P<location>\[string "(?P<path>[^"]+)"\]:(?P<line>\d+))(?P<rest>.*)$"#)
+        .unwrap()
+});
+
+/// `ServerScriptService.Foo:12`, `ServerScriptService.Foo:12 function bar`, `ServerScriptService.Foo:12: message`
+///
+/// 路径必须是不含空白的 `.` 分隔的 DataModel 路径, 避免把 `meeting at 12:30` 之类的消息文本当作堆栈帧
+static PLAIN_FRAME: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"^\s*(?P<location>(?P<path>[^\s'"\[\]:.]+(?:\.[^\s'"\[\]:]+)+):(?P<line>\d+))(?P<rest>$|[:\s].*$)"#,
+    )
+    .unwrap()
+});
+
+/// 堆栈末尾的函数名: ` function bar`
+static FUNCTION_SUFFIX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\s+(?:function\s+)?(?P<function>\S.*?)\s*$").unwrap());
+
+/// 默认视为框架内部的堆栈帧: Jest 本身以及通过 npm/Wally 安装的依赖包
+const DEFAULT_FRAME_FILTER_PATTERNS: &[&str] =
+    &[r"(^|\.)node_modules\.", r"(^|\.)_Index\.", r"@jsdotlua\."];
+
+/// 框架堆栈帧过滤规则, 按 DataModel 路径匹配
+#[derive(Debug, Clone)]
+pub struct FrameFilter {
+    patterns: Vec<Regex>,
+    /// 是否在输出中折叠框架帧, `--full-stack` 时为 false
+    collapse: bool,
+}
+
+/// 解析后的 Luau 堆栈帧
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StackFrame {
+    /// 脚本的 DataModel 路径, 如 `ServerScriptService.Foo.Bar`
+    pub datamodel_path: String,
+    pub line: u32,
+    pub function: Option<String>,
+    /// 原始文本
+    pub raw: String,
+    /// `raw` 中表示位置(路径和行号)的部分, 转换时只替换这部分
+    location: Range<usize>,
+}
+
+/// 堆栈文本中的一行: 堆栈帧或其他文本(错误消息、`Stack Begin` 等)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StackLine {
+    Frame(StackFrame),
+    Text(String),
+}
+
+impl StackFrame {
+    /// 解析一行堆栈, 支持 Roblox 输出的所有格式; 不是堆栈帧时返回 None
+    pub fn parse(line: &str) -> Option<Self> {
+        if let Some(captures) = SCRIPT_LINE_FRAME.captures(line) {
+            let function = captures.name("function").map(|m| m.as_str().trim());
+            return Self::from_captures(line, &captures, function);
+        }
+
+        let captures = STRING_CHUNK_FRAME
+            .captures(line)
+            .or_else(|| PLAIN_FRAME.captures(line))?;
+        let function = captures
+            .name("rest")
+            .and_then(|rest| FUNCTION_SUFFIX.captures(rest.as_str()))
+            .and_then(|captures| captures.name("function"))
+            .map(|m| m.as_str());
+        Self::from_captures(line, &captures, function)
+    }
+
+    fn from_captures(line: &str, captures: &Captures, function: Option<&str>) -> Option<Self> {
+        Some(StackFrame {
+            datamodel_path: captures.name("path")?.as_str().trim().to_string(),
+            line: captures.name("line")?.as_str().parse().ok()?,
+            function: function.map(str::to_string),
+            raw: line.to_string(),
+            location: captures.name("location")?.range(),
+        })
+    }
+
+    /// 将原始文本中的位置替换为 `location`
+    fn with_location(&self, location: &str) -> String {
+        format!(
+            "{}{}{}",
+            &self.raw[..self.location.start],
+            location,
+            &self.raw[self.location.end..]
+        )
+    }
+
+    /// 在 Sourcemap 中查找该帧对应的本地文件
+    pub fn resolve<'a>(&self, sourcemap: &'a RojoSourceMap) -> Lookup<'a> {
+        sourcemap.lookup(&self.datamodel_path)
+    }
+
+    /// 将位置替换为本地路径后的文本; 找不到对应文件时返回原始文本, 有多个候选时列出所有候选
+    pub fn to_local(&self, sourcemap: Option<&RojoSourceMap>, links: &FileLinks) -> String {
+        let Some(sourcemap) = sourcemap else {
+            return self.raw.clone();
+        };
+
+        match self.resolve(sourcemap) {
+            Lookup::Found(found) => self.with_location(&links.location(found.file_path, self.line)),
+            Lookup::Ambiguous(candidates) => {
+                let candidates: Vec<String> = candidates
+                    .iter()
+                    .map(|candidate| links.display(candidate.file_path))
+                    .collect();
+                format!("{} (匹配到多个文件: {})", self.raw, candidates.join(", "))
+            }
+            Lookup::NotFound => self.raw.clone(),
+        }
+    }
+}
+
+impl FrameFilter {
+    /// 默认规则加上配置中的 `stackIgnorePatterns`
+    pub fn new(extra_patterns: &[String], collapse: bool) -> Result<Self, regex::Error> {
+        let patterns = DEFAULT_FRAME_FILTER_PATTERNS
+            .iter()
+            .copied()
+            .chain(extra_patterns.iter().map(String::as_str))
+            .map(Regex::new)
+            .collect::<Result<_, _>>()?;
+        Ok(FrameFilter { patterns, collapse })
+    }
+
+    /// 是否为 Jest 或依赖包内部的堆栈帧
+    pub fn is_framework(&self, frame: &StackFrame) -> bool {
+        self.patterns
+            .iter()
+            .any(|pattern| pattern.is_match(&frame.datamodel_path))
+    }
+}
+
+impl Default for FrameFilter {
+    fn default() -> Self {
+        FrameFilter::new(&[], true).unwrap()
+    }
+}
+
+impl StackLine {
+    pub fn to_local(&self, sourcemap: Option<&RojoSourceMap>, links: &FileLinks) -> String {
+        match self {
+            StackLine::Frame(frame) => frame.to_local(sourcemap, links),
+            StackLine::Text(text) => text.clone(),
+        }
+    }
+}
+
+/// 逐行解析堆栈文本
+pub fn parse_stack(text: &str) -> Vec<StackLine> {
+    text.split('\n')
+        .map(|line| match StackFrame::parse(line) {
+            Some(frame) => StackLine::Frame(frame),
+            None => StackLine::Text(line.to_string()),
+        })
+        .collect()
+}
+
+/// 第一个不属于框架的堆栈帧, 即用户代码中的失败位置
+pub fn first_user_frame<'a>(
+    stack: &'a [StackLine],
+    filter: &FrameFilter,
+) -> Option<&'a StackFrame> {
+    stack.iter().find_map(|line| match line {
+        StackLine::Frame(frame) if !filter.is_framework(frame) => Some(frame),
+        _ => None,
+    })
+}
+
+/// 转换为本地路径后的各行, 连续的框架帧折叠为一行提示
+///
+/// 堆栈中没有用户代码的帧时不折叠, 避免丢失全部位置信息
+pub fn render_stack(
+    stack: &[StackLine],
+    sourcemap: Option<&RojoSourceMap>,
+    filter: &FrameFilter,
+    links: &FileLinks,
+) -> Vec<String> {
+    let collapse = filter.collapse && first_user_frame(stack, filter).is_some();
+
+    let mut lines = Vec::new();
+    let mut hidden = 0;
+    let mut indent = "";
+    for line in stack {
+        if let StackLine::Frame(frame) = line {
+            if collapse && filter.is_framework(frame) {
+                if hidden == 0 {
+                    indent = &frame.raw[..frame.raw.len() - frame.raw.trim_start().len()];
+                }
+                hidden += 1;
+                continue;
+            }
+        }
+
+        if hidden > 0 {
+            lines.push(collapsed_frames(indent, hidden));
+            hidden = 0;
+        }
+        lines.push(line.to_local(sourcemap, links));
+    }
+    if hidden > 0 {
+        lines.push(collapsed_frames(indent, hidden));
+    }
+
+    lines
+}
+
+fn collapsed_frames(indent: &str, count: usize) -> String {
+    format!("{}... 已隐藏 {} 个框架堆栈帧", indent, count)
+}
+
+/// 只把能找到本地文件的位置替换为本地路径, 不折叠框架帧也不添加提示, 用于机器读取的输出
+pub fn localize_stack_text(
+    text: &str,
+    sourcemap: Option<&RojoSourceMap>,
+    links: &FileLinks,
+) -> String {
+    parse_stack(text)
+        .iter()
+        .map(|line| match line {
+            StackLine::Frame(frame) => match sourcemap.map(|sourcemap| frame.resolve(sourcemap)) {
+                Some(Lookup::Found(found)) => {
+                    frame.with_location(&links.location(found.file_path, frame.line))
+                }
+                _ => frame.raw.clone(),
+            },
+            StackLine::Text(text) => text.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// 将堆栈文本中的位置转换为本地路径, 并折叠框架帧
+pub fn convert_stack_trace_text(
+    text: &str,
+    sourcemap: Option<&RojoSourceMap>,
+    filter: &FrameFilter,
+    links: &FileLinks,
+) -> String {
+    render_stack(&parse_stack(text), sourcemap, filter, links).join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> (String, u32, Option<String>) {
+        let frame = StackFrame::parse(line).unwrap_or_else(|| panic!("未解析: {}", line));
+        (frame.datamodel_path, frame.line, frame.function)
+    }
+
+    #[test]
+    fn parses_script_line_frames() {
+        assert_eq!(
+            parse("Script 'ServerScriptService.Foo', Line 12 - function bar"),
+            ("ServerScriptService.Foo".into(), 12, Some("bar".into()))
+        );
+        assert_eq!(
+            parse("  Script 'ReplicatedStorage.My Module', Line 3"),
+            ("ReplicatedStorage.My Module".into(), 3, None)
+        );
+    }
+
+    #[test]
+    fn parses_string_chunk_frames() {
+        assert_eq!(
+            parse(r#"[string "ServerScriptService.Foo"]:12: attempt to index nil"#),
+            ("ServerScriptService.Foo".into(), 12, None)
+        );
+        assert_eq!(
+            parse(r#"[string "ServerScriptService.Foo"]:7 function run"#),
+            ("ServerScriptService.Foo".into(), 7, Some("run".into()))
+        );
+    }
+
+    #[test]
+    fn parses_plain_frames_with_function() {
+        assert_eq!(
+            parse("ServerScriptService.Foo:12 function bar"),
+            ("ServerScriptService.Foo".into(), 12, Some("bar".into()))
+        );
+        assert_eq!(
+            parse("    ReplicatedStorage.Packages.os-lib.spec:40"),
+            ("ReplicatedStorage.Packages.os-lib.spec".into(), 40, None)
+        );
+    }
+
+    #[test]
+    fn parses_plain_frames_with_message() {
+        assert_eq!(
+            parse("ServerScriptService.Foo:12: attempt to call a nil value"),
+            ("ServerScriptService.Foo".into(), 12, None)
+        );
+        assert_eq!(
+            parse("ServerScriptService.ErrorHandler:5: boom"),
+            ("ServerScriptService.ErrorHandler".into(), 5, None)
+        );
+    }
+
+    #[test]
+    fn location_covers_path_and_line_only() {
+        let frame = StackFrame::parse("  ServerScriptService.Foo:12: boom").unwrap();
+        assert_eq!(
+            &frame.raw[frame.location.clone()],
+            "ServerScriptService.Foo:12"
+        );
+    }
+
+    #[test]
+    fn ignores_message_text() {
+        for line in [
+            "Expected: 12:30",
+            "Received: 1:2",
+            "Error: timeout after 5:00",
+            "Error:5",
+            "12:30",
+            "meeting at 12:30",
+            "Timeout after 5:00",
+            "  expected the job to finish by 23:59 today",
+            "took 1.5 s, deadline 0:30",
+            "Stack Begin",
+            "Stack End",
+            "",
+        ] {
+            assert_eq!(StackFrame::parse(line), None, "{}", line);
+        }
+    }
+}
@@ -1,9 +1,21 @@
 use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
-use tokio::sync::Mutex as TokioMutex;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+use tokio::sync::{mpsc::UnboundedSender, Mutex as TokioMutex};
 
-use crate::config::Config;
+use crate::{
+    config::{Config, RunCliOptions},
+    exit_code::ExitCode,
+    places::PlaceFilter,
+    reporters::ReportOptions,
+    sourcemap_cache::SourcemapCache,
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Place {
@@ -16,7 +28,62 @@ pub struct AppState {
 
     pub places: DashMap<String, Place>,
     pub active_place: TokioMutex<Option<String>>,
+    /// `--place-*` 参数指定的目标place
+    pub place_filter: PlaceFilter,
+    /// 等待匹配place连接的时间
+    pub place_timeout: Duration,
+    /// 是否有待运行的测试, 下一次活跃place的 `/poll` 会领取它
+    pub run_pending: AtomicBool,
+    /// 最近一次下发测试的时间, 收到结果后清空
+    pub dispatched_at: Mutex<Option<Instant>>,
+    /// 没有任何place连接时的超时时间
+    pub connect_timeout: Option<Duration>,
+    /// 下发测试后等待结果的超时时间
+    pub run_timeout: Option<Duration>,
+    /// 发送给插件的 runCLI-options, 监听模式下可通过按键修改
+    pub run_cli_options: Mutex<Option<RunCliOptions>>,
+    /// 仅对下一次运行生效的 updateSnapshot
+    pub update_snapshot_once: AtomicBool,
+    /// 上一次运行中失败的测试全名
+    pub last_failed_tests: Mutex<Vec<String>>,
+    /// 监听模式的按键处理线程是否在运行, 只有此时才提示可用的按键
+    pub key_handler_active: AtomicBool,
 
-    pub only_log_failures: bool,
+    pub sourcemap: SourcemapCache,
+    pub report_options: ReportOptions,
     pub debug: bool,
+    /// 监听模式: 收到测试结果后不退出, 文件变更时重新运行
+    pub watch: bool,
+    /// 通知 `main` 关闭服务器并以给定退出码结束, 只有第一个退出码生效
+    pub shutdown: UnboundedSender<ExitCode>,
+}
+
+impl AppState {
+    /// 当前的 runCLI-options
+    pub fn run_cli_options(&self) -> Option<RunCliOptions> {
+        self.run_cli_options.lock().unwrap().clone()
+    }
+
+    /// 领取下一次运行使用的 runCLI-options, 一次性选项在此被消耗
+    pub fn take_run_cli_options(&self) -> Option<RunCliOptions> {
+        let mut options = self.run_cli_options();
+        if self.update_snapshot_once.swap(false, Ordering::SeqCst) {
+            options
+                .get_or_insert_with(RunCliOptions::default)
+                .update_snapshot = Some(true);
+        }
+        options
+    }
+
+    /// 请求优雅关闭服务器, 由 `main` 在当前请求完成后返回退出码
+    pub fn shutdown(&self, exit_code: ExitCode) {
+        // 接收端已关闭说明服务器正在关闭, 忽略即可
+        let _ = self.shutdown.send(exit_code);
+    }
+
+    /// 修改之后运行使用的 runCLI-options
+    pub fn update_run_cli_options(&self, update: impl FnOnce(&mut RunCliOptions)) {
+        let mut options = self.run_cli_options.lock().unwrap();
+        update(options.get_or_insert_with(RunCliOptions::default));
+    }
 }
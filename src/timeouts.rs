@@ -0,0 +1,87 @@
+use crate::{exit_code::ExitCode, server, state::AppState};
+use console::style;
+use std::{
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::time::sleep;
+
+/// 检查超时的间隔
+const TIMEOUT_CHECK_INTERVAL: Duration = Duration::from_millis(200);
+
+/// 在后台检查连接超时和运行超时, 超时后输出排查建议并以对应退出码关闭服务器
+pub async fn monitor_timeouts(state: Arc<AppState>, addr: SocketAddr) {
+    let started_at = Instant::now();
+
+    loop {
+        sleep(TIMEOUT_CHECK_INTERVAL).await;
+
+        if let Some(connect_timeout) = state.connect_timeout {
+            if state.places.is_empty() && started_at.elapsed() >= connect_timeout {
+                report_connect_timeout(connect_timeout, addr);
+                state.shutdown(ExitCode::ConnectTimeout);
+                return;
+            }
+        }
+
+        if let Some(run_timeout) = state.run_timeout {
+            let dispatched_at = *state.dispatched_at.lock().unwrap();
+            if dispatched_at.is_some_and(|at| at.elapsed() >= run_timeout) {
+                report_run_timeout(&state, run_timeout).await;
+                state.shutdown(ExitCode::RunTimeout);
+                return;
+            }
+        }
+    }
+}
+
+fn report_connect_timeout(timeout: Duration, addr: SocketAddr) {
+    eprintln!(
+        "{}",
+        style(format!(
+            "{}秒内没有收到任何 Studio 插件的连接",
+            timeout.as_secs()
+        ))
+        .red()
+        .bold()
+    );
+    eprintln!("请检查:");
+    eprintln!("  1. Roblox Studio 已打开测试所在的 Place");
+    eprintln!(
+        "  2. 插件已安装: 运行 `npx jest-lua-companion init`, 或复制 plugin/jest-lua-companion-plugin.server.lua 到 Studio 插件目录后重启 Studio"
+    );
+    eprintln!(
+        "  3. 端口一致: CLI 监听 http://{}, 插件会尝试 {}:{}-{}",
+        addr,
+        server::DEFAULT_HOST,
+        server::DEFAULT_PORT,
+        server::DEFAULT_PORT + server::PORT_RANGE - 1
+    );
+}
+
+async fn report_run_timeout(state: &AppState, timeout: Duration) {
+    let active_place = state.active_place.lock().await.clone();
+    let place = active_place
+        .as_deref()
+        .and_then(|guid| state.places.get(guid))
+        .map(|place| format!("{} ({})", place.name, place.id))
+        .unwrap_or_default();
+
+    eprintln!(
+        "{}",
+        style(format!(
+            "测试已下发到place {}, 但{}秒内没有收到测试结果",
+            place,
+            timeout.as_secs()
+        ))
+        .red()
+        .bold()
+    );
+    eprintln!("请检查:");
+    eprintln!(
+        "  1. FFlag 已设置: Studio 安装目录下 ClientSettings/ClientAppSettings.json 中 FFlagEnableLoadModule 为 true (可运行 `npx jest-lua-companion init`), 修改后需重启 Studio"
+    );
+    eprintln!("  2. Studio 输出窗口中是否有插件或 Jest 的报错");
+    eprintln!("  3. 是否有测试卡住, 或 testTimeout 大于 --run-timeout");
+}
@@ -0,0 +1,402 @@
+use crate::{config::Config, exit_code::ExitCode, rojo_sourcemap::RojoSourceMap, state::AppState};
+use console::{style, Key, Term};
+use std::{
+    collections::{BTreeSet, HashMap},
+    io::IsTerminal,
+    path::{Component, Path, PathBuf},
+    sync::{atomic::Ordering, Arc},
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+/// 扫描文件变更的间隔
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+/// 最后一次文件变更后等待的时间, 避免 Rojo 同步多个文件时重复运行
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// 监听目标
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum WatchTarget {
+    /// 监听目录下的文件(不递归)
+    Dir(PathBuf),
+    /// 递归监听目录下的所有文件和子目录
+    Tree(PathBuf),
+    /// 监听匹配 glob 的文件
+    Glob(String),
+}
+
+/// 确定要监听的路径: 优先使用配置中的 `watchPaths`, 否则使用 Sourcemap 引用文件所在的目录
+pub fn collect_watch_targets(
+    config: &Config,
+    sourcemap: Option<&RojoSourceMap>,
+) -> Vec<WatchTarget> {
+    if let Some(patterns) = &config.watch_paths {
+        return patterns
+            .iter()
+            .filter(|pattern| match glob::Pattern::new(pattern) {
+                Ok(_) => true,
+                Err(e) => {
+                    eprintln!(
+                        "{}",
+                        style(format!("无效的 watchPaths 模式 \"{}\": {}", pattern, e)).yellow()
+                    );
+                    false
+                }
+            })
+            .map(|pattern| WatchTarget::Glob(pattern.clone()))
+            .collect();
+    }
+
+    let Some(sourcemap) = sourcemap else {
+        return Vec::new();
+    };
+
+    // 递归监听源码所在的顶层目录, 之后新建的子目录也能监听到; 基准目录本身(项目文件所在)不递归,
+    // 避免扫描 node_modules 等无关目录
+    let base_dir = config.sourcemap_base_dir();
+    let targets: BTreeSet<WatchTarget> = sourcemap
+        .file_paths()
+        .into_iter()
+        .map(|path| watch_target(&base_dir, Path::new(path)))
+        .collect();
+
+    targets.into_iter().collect()
+}
+
+/// Sourcemap 中的文件对应的监听目标
+fn watch_target(base_dir: &Path, file_path: &Path) -> WatchTarget {
+    if !file_path.is_absolute() {
+        let mut components = file_path
+            .components()
+            .filter(|component| matches!(component, Component::Normal(_)));
+        if let (Some(top), Some(_)) = (components.next(), components.next()) {
+            return WatchTarget::Tree(base_dir.join(top));
+        }
+    }
+
+    match base_dir.join(file_path).parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => WatchTarget::Dir(dir.to_path_buf()),
+        _ => WatchTarget::Dir(PathBuf::from(".")),
+    }
+}
+
+/// 按 Sourcemap 收集监听目标; `changed` 为 true 时重新生成 Sourcemap, 以包含新建的顶层目录
+fn current_watch_targets(state: &AppState, changed: bool) -> Vec<WatchTarget> {
+    let sourcemap = if changed {
+        state.sourcemap.regenerate(&state.config, state.debug)
+    } else {
+        state.sourcemap.load(&state.config, state.debug)
+    };
+    collect_watch_targets(&state.config, sourcemap.as_deref())
+}
+
+/// 在后台线程中监听文件变更, 变更稳定后在下一次 `/poll` 时重新运行测试
+pub fn spawn_watcher(state: Arc<AppState>) {
+    let targets = current_watch_targets(&state, false);
+    if targets.is_empty() {
+        eprintln!(
+            "{}",
+            style("未找到可监听的路径, 请配置 rojoSourceMapCommand 或 watchPaths").yellow()
+        );
+        return;
+    }
+
+    if state.debug {
+        eprintln!("监听路径: {:?}", targets);
+    }
+
+    thread::spawn(move || {
+        let mut targets = targets;
+        let mut previous = snapshot(&targets);
+        let mut last_change: Option<Instant> = None;
+
+        loop {
+            thread::sleep(WATCH_INTERVAL);
+
+            let current = snapshot(&targets);
+            if current != previous {
+                previous = current;
+                last_change = Some(Instant::now());
+                continue;
+            }
+
+            if last_change.is_some_and(|changed_at| changed_at.elapsed() >= WATCH_DEBOUNCE) {
+                last_change = None;
+                state.run_pending.store(true, Ordering::SeqCst);
+                eprintln!(
+                    "{}",
+                    style("检测到文件变更, 将在下一次轮询时重新运行测试...").cyan()
+                );
+
+                // 项目文件中新增的顶层目录要在重新收集后才会被监听; glob 每次扫描时都会重新匹配, 无需重新收集
+                if state.config.watch_paths.is_none() {
+                    targets = current_watch_targets(&state, true);
+                    previous = snapshot(&targets);
+                }
+            }
+        }
+    });
+}
+
+/// 输出监听模式下可用的按键; 按键处理没有启用时(例如在CI中)不输出
+pub fn print_watch_usage(state: &AppState) {
+    if !state.key_handler_active.load(Ordering::SeqCst) {
+        return;
+    }
+
+    eprintln!();
+    eprintln!("{}", style("Watch Usage").bold());
+    for (key, description) in [
+        ("a", "运行所有测试"),
+        ("f", "只运行失败的测试"),
+        ("p", "按文件路径过滤 (testPathPattern)"),
+        ("t", "按测试名称过滤 (testNamePattern)"),
+        ("u", "更新失败的快照"),
+        ("q", "退出"),
+        ("Enter", "重新运行测试"),
+    ] {
+        eprintln!(
+            "{} {} {}",
+            style(" › 按").dim(),
+            style(key).bold(),
+            style(description).dim()
+        );
+    }
+}
+
+/// 启动时的终端状态, 退出时恢复
+///
+/// `read_key` 读取时会把终端切换到原始模式; 服务器因超时或运行中按下 `q` 等原因关闭时,
+/// 读取按键的线程可能仍在等待, 终端会停留在原始模式
+pub struct TerminalGuard {
+    #[cfg(unix)]
+    original: Option<libc::termios>,
+}
+
+impl TerminalGuard {
+    pub fn save() -> Self {
+        #[cfg(unix)]
+        {
+            let mut termios = std::mem::MaybeUninit::uninit();
+            // SAFETY: tcgetattr 成功时会完整写入 termios
+            let original = unsafe {
+                (libc::tcgetattr(libc::STDIN_FILENO, termios.as_mut_ptr()) == 0)
+                    .then(|| termios.assume_init())
+            };
+            TerminalGuard { original }
+        }
+        #[cfg(not(unix))]
+        TerminalGuard {}
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Some(original) = &self.original {
+            // SAFETY: original 来自 tcgetattr
+            unsafe {
+                libc::tcsetattr(libc::STDIN_FILENO, libc::TCSADRAIN, original);
+            }
+        }
+        let term = Term::stderr();
+        if term.is_term() {
+            let _ = term.show_cursor();
+        }
+    }
+}
+
+/// 在后台线程中读取单个按键, 修改下一次 `/poll` 发送的 runCLI-options
+///
+/// 标准输入不是终端时(例如在CI中)不启用
+pub fn spawn_key_handler(state: Arc<AppState>) {
+    // 按键从 stderr 所在的终端读取, stdin 和 stderr 都必须是终端
+    let term = Term::stderr();
+    if !std::io::stdin().is_terminal() || !term.is_term() {
+        return;
+    }
+
+    state.key_handler_active.store(true, Ordering::SeqCst);
+    thread::spawn(move || {
+        handle_keys(&state, &term);
+        state.key_handler_active.store(false, Ordering::SeqCst);
+    });
+}
+
+fn handle_keys(state: &Arc<AppState>, term: &Term) {
+    loop {
+        let key = match term.read_key() {
+            // 不是终端时 read_key 会立即返回 Unknown, 继续读取会空转
+            Ok(Key::Unknown) => return,
+            Ok(key) => key,
+            Err(e) => {
+                if state.debug {
+                    eprintln!("读取按键失败: {}", e);
+                }
+                return;
+            }
+        };
+
+        match key {
+            Key::Char('a') => {
+                let defaults = state.config.run_cli_options.clone().unwrap_or_default();
+                state.update_run_cli_options(|options| {
+                    options.test_name_pattern = defaults.test_name_pattern;
+                    options.test_path_pattern = defaults.test_path_pattern;
+                });
+                request_run(state, "运行所有测试");
+            }
+            Key::Char('f') => {
+                let failed_tests = state.last_failed_tests.lock().unwrap().clone();
+                if failed_tests.is_empty() {
+                    eprintln!("{}", style("上一次运行没有失败的测试").yellow());
+                    continue;
+                }
+                let pattern = failed_tests_pattern(&failed_tests);
+                state.update_run_cli_options(|options| {
+                    options.test_name_pattern = Some(pattern);
+                });
+                request_run(state, "只运行失败的测试");
+            }
+            Key::Char('p') => {
+                let current = state
+                    .run_cli_options()
+                    .and_then(|options| options.test_path_pattern)
+                    .unwrap_or_default();
+                if let Some(pattern) = prompt_pattern("testPathPattern", &current) {
+                    state.update_run_cli_options(|options| {
+                        options.test_path_pattern = pattern;
+                    });
+                    request_run(state, "按文件路径过滤");
+                }
+            }
+            Key::Char('t') => {
+                let current = state
+                    .run_cli_options()
+                    .and_then(|options| options.test_name_pattern)
+                    .unwrap_or_default();
+                if let Some(pattern) = prompt_pattern("testNamePattern", &current) {
+                    state.update_run_cli_options(|options| {
+                        options.test_name_pattern = pattern;
+                    });
+                    request_run(state, "按测试名称过滤");
+                }
+            }
+            Key::Char('u') => {
+                state.update_snapshot_once.store(true, Ordering::SeqCst);
+                request_run(state, "更新快照");
+            }
+            Key::Char('q') => {
+                state.shutdown(ExitCode::Success);
+                return;
+            }
+            Key::Enter => request_run(state, "重新运行测试"),
+            _ => {}
+        }
+    }
+}
+
+fn request_run(state: &AppState, reason: &str) {
+    state.run_pending.store(true, Ordering::SeqCst);
+    eprintln!(
+        "{}",
+        style(format!("{}, 将在下一次轮询时运行...", reason)).cyan()
+    );
+}
+
+/// 提示输入过滤模式, 输入为空时清除过滤; 取消输入时返回 None
+fn prompt_pattern(name: &str, current: &str) -> Option<Option<String>> {
+    let input = inquire::Text::new(&format!("{}:", name))
+        .with_initial_value(current)
+        .with_help_message("留空清除过滤, Esc 取消")
+        .prompt()
+        .ok()?;
+
+    let input = input.trim();
+    Some((!input.is_empty()).then(|| input.to_string()))
+}
+
+/// 将失败测试的全名组合为精确匹配的 testNamePattern
+fn failed_tests_pattern(failed_tests: &[String]) -> String {
+    let names: Vec<String> = failed_tests
+        .iter()
+        .map(|name| regex::escape(name))
+        .collect();
+    format!("^({})$", names.join("|"))
+}
+
+/// 记录所有监听文件的修改时间
+fn snapshot(targets: &[WatchTarget]) -> HashMap<PathBuf, SystemTime> {
+    let mut files = HashMap::new();
+
+    for target in targets {
+        match target {
+            WatchTarget::Dir(dir) => {
+                let Ok(entries) = std::fs::read_dir(dir) else {
+                    continue;
+                };
+                for entry in entries.flatten() {
+                    insert_modified(&mut files, entry.path());
+                }
+            }
+            WatchTarget::Tree(dir) => insert_tree(&mut files, dir),
+            WatchTarget::Glob(pattern) => {
+                let Ok(paths) = glob::glob(pattern) else {
+                    continue;
+                };
+                for path in paths.flatten() {
+                    insert_modified(&mut files, path);
+                }
+            }
+        }
+    }
+
+    files
+}
+
+/// 递归记录目录下所有文件和子目录的修改时间, 不跟随符号链接
+fn insert_tree(files: &mut HashMap<PathBuf, SystemTime>, dir: &Path) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if entry.file_type().is_ok_and(|file_type| file_type.is_dir()) {
+            insert_tree(files, &path);
+        }
+        insert_modified(files, path);
+    }
+}
+
+fn insert_modified(files: &mut HashMap<PathBuf, SystemTime>, path: PathBuf) {
+    if let Ok(modified) = std::fs::metadata(&path).and_then(|meta| meta.modified()) {
+        files.insert(path, modified);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn watches_top_level_source_dirs_recursively() {
+        let base_dir = PathBuf::from("game");
+
+        assert_eq!(
+            watch_target(&base_dir, Path::new("src/server/init.lua")),
+            WatchTarget::Tree(PathBuf::from("game/src"))
+        );
+        assert_eq!(
+            watch_target(&base_dir, Path::new("default.project.json")),
+            WatchTarget::Dir(PathBuf::from("game"))
+        );
+        assert_eq!(
+            watch_target(Path::new(""), Path::new("default.project.json")),
+            WatchTarget::Dir(PathBuf::from("."))
+        );
+        assert_eq!(
+            watch_target(&base_dir, Path::new("/opt/lib/init.lua")),
+            WatchTarget::Dir(PathBuf::from("/opt/lib"))
+        );
+    }
+}